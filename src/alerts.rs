@@ -0,0 +1,639 @@
+//! Rule-based alerting on the fused fleet state.
+//!
+//! Rules are evaluated for every drone each time the dashboard refreshes. A rule
+//! raises an alert once its condition has held for `for_s` seconds (debounce), and
+//! the alert clears once the condition has been false for `clear_after_s` seconds
+//! (hysteresis). Alerts stay in the history after clearing; once it is full the
+//! oldest cleared ones age out, acknowledged or not.
+//!
+//! ```json
+//! {
+//!   "rules": [
+//!     { "name": "Low battery", "severity": "critical", "when": "battery_below",
+//!       "pct": 20, "for_s": 2, "clear_after_s": 10, "sound": true },
+//!     { "name": "Stale", "severity": "warning", "when": "stale", "secs": 5 },
+//!     { "name": "Altitude", "severity": "warning", "when": "altitude_outside",
+//!       "min": 5, "max": 120, "for_s": 3 },
+//!     { "name": "Too fast", "severity": "warning", "when": "speed_above", "speed": 15 },
+//!     { "name": "Status", "severity": "info", "when": "status_not", "status": "OK" },
+//!     { "name": "No-fly zone", "severity": "critical", "when": "geofence", "fence": "Mast" }
+//!   ]
+//! }
+//! ```
+//!
+//! Times on alerts are wall-clock ms since the Unix epoch (the replay cursor while
+//! replaying), so the history can be exported as is.
+
+use crate::{
+    fusion::{AppState, DroneState},
+    telemetry::LOW_BATTERY_PCT,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt, fs, io,
+    path::Path,
+    time::Instant,
+};
+
+/// Alerts kept in the history, active ones included.
+pub const ALERTS_MAX: usize = 1000;
+
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    #[default]
+    Info,
+    Warning,
+    Critical,
+}
+
+impl Severity {
+    pub fn label(self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Critical => "critical",
+        }
+    }
+}
+
+/// What a rule looks for in a drone's fused state.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "when", rename_all = "snake_case")]
+pub enum Condition {
+    BatteryBelow {
+        pct: f32,
+    },
+    /// No packet for longer than `secs`.
+    Stale {
+        secs: f32,
+    },
+    AltitudeOutside {
+        #[serde(default)]
+        min: Option<f32>,
+        #[serde(default)]
+        max: Option<f32>,
+    },
+    SpeedAbove {
+        speed: f32,
+    },
+    StatusNot {
+        #[serde(default = "default_status")]
+        status: String,
+    },
+    /// Breaching the named geofence, or any geofence if no name is given.
+    Geofence {
+        #[serde(default)]
+        fence: Option<String>,
+    },
+}
+
+fn default_status() -> String {
+    "OK".to_string()
+}
+
+impl Condition {
+    /// A description of the violation if the condition holds for `d` at `now`.
+    fn check(&self, d: &DroneState, now: Instant) -> Option<String> {
+        match self {
+            Condition::BatteryBelow { pct } => {
+                (d.battery < *pct).then(|| format!("battery {:.0}% < {pct:.0}%", d.battery))
+            }
+            Condition::Stale { secs } => {
+                let age = now.saturating_duration_since(d.last_seen).as_secs_f32();
+                (age > *secs).then(|| format!("no packet for {age:.0} s"))
+            }
+            Condition::AltitudeOutside { min, max } => {
+                if min.is_some_and(|m| d.z < m) {
+                    Some(format!(
+                        "altitude {:.1} < {:.1}",
+                        d.z,
+                        min.unwrap_or_default()
+                    ))
+                } else if max.is_some_and(|m| d.z > m) {
+                    Some(format!(
+                        "altitude {:.1} > {:.1}",
+                        d.z,
+                        max.unwrap_or_default()
+                    ))
+                } else {
+                    None
+                }
+            }
+            Condition::SpeedAbove { speed } => {
+                let v = d.speed()?;
+                (v > *speed).then(|| format!("speed {v:.1} > {speed:.1}"))
+            }
+            Condition::StatusNot { status } => {
+                (!d.status.eq_ignore_ascii_case(status)).then(|| format!("status {}", d.status))
+            }
+            Condition::Geofence { fence } => d
+                .breaches
+                .iter()
+                .find(|b| fence.as_ref().is_none_or(|f| *f == b.fence))
+                .map(|b| format!("{} {}", b.kind.label(), b.fence)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rule {
+    pub name: String,
+    pub severity: Severity,
+    #[serde(flatten)]
+    pub condition: Condition,
+    /// Seconds the condition must hold before the alert is raised.
+    #[serde(default)]
+    pub for_s: f32,
+    /// Seconds the condition must stay false before the alert clears.
+    #[serde(default = "default_clear_after")]
+    pub clear_after_s: f32,
+    /// Only these drones; all drones if absent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub drones: Option<Vec<u32>>,
+    /// Flash the dashboard when raised.
+    #[serde(default = "default_true")]
+    pub flash: bool,
+    /// Ring the terminal bell when raised.
+    #[serde(default)]
+    pub sound: bool,
+}
+
+fn default_clear_after() -> f32 {
+    2.0
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Rule {
+    fn new(name: &str, severity: Severity, condition: Condition) -> Self {
+        Self {
+            name: name.to_string(),
+            severity,
+            condition,
+            for_s: 0.0,
+            clear_after_s: default_clear_after(),
+            drones: None,
+            flash: severity == Severity::Critical,
+            sound: false,
+        }
+    }
+}
+
+/// Rules used when no rules file is given: what the dashboard used to show implicitly.
+pub fn default_rules() -> Vec<Rule> {
+    vec![
+        Rule::new(
+            "Low battery",
+            Severity::Critical,
+            Condition::BatteryBelow {
+                pct: LOW_BATTERY_PCT,
+            },
+        ),
+        Rule::new("Stale", Severity::Warning, Condition::Stale { secs: 5.0 }),
+        Rule::new(
+            "Status",
+            Severity::Warning,
+            Condition::StatusNot {
+                status: default_status(),
+            },
+        ),
+        Rule::new(
+            "Geofence",
+            Severity::Critical,
+            Condition::Geofence { fence: None },
+        ),
+    ]
+}
+
+#[derive(Debug)]
+pub enum RulesError {
+    Io(io::Error),
+    Parse(serde_json::Error),
+}
+
+impl fmt::Display for RulesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RulesError::Io(e) => write!(f, "cannot read alert rules: {e}"),
+            RulesError::Parse(e) => write!(f, "invalid alert rules: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for RulesError {}
+
+#[derive(Deserialize)]
+struct RulesFile {
+    rules: Vec<Rule>,
+}
+
+pub fn load_rules(path: impl AsRef<Path>) -> Result<Vec<Rule>, RulesError> {
+    let text = fs::read_to_string(path).map_err(RulesError::Io)?;
+    let file: RulesFile = serde_json::from_str(&text).map_err(RulesError::Parse)?;
+    Ok(file.rules)
+}
+
+/// One raised alert, active or cleared.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Alert {
+    pub id: u64,
+    pub drone: u32,
+    pub rule: String,
+    pub severity: Severity,
+    /// Latest description of the violation.
+    pub message: String,
+    pub first_ms: u128,
+    pub last_ms: u128,
+    pub cleared_ms: Option<u128>,
+    pub acked_by: Option<String>,
+    pub acked_ms: Option<u128>,
+}
+
+impl Alert {
+    pub fn active(&self) -> bool {
+        self.cleared_ms.is_none()
+    }
+
+    pub fn acked(&self) -> bool {
+        self.acked_by.is_some()
+    }
+}
+
+/// File formats for [`export_alerts`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+/// Quote a CSV field if it needs it.
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// Write alerts to `path`, one row (CSV) or object (JSON) per alert. Times are
+/// ms since the Unix epoch; empty / `null` when not cleared or acknowledged.
+pub fn export_alerts(
+    path: impl AsRef<Path>,
+    alerts: &[Alert],
+    format: ExportFormat,
+) -> io::Result<()> {
+    let text = match format {
+        ExportFormat::Json => serde_json::to_string_pretty(alerts).map_err(io::Error::other)?,
+        ExportFormat::Csv => {
+            let opt = |v: Option<u128>| v.map(|v| v.to_string()).unwrap_or_default();
+            let mut out = String::from(
+                "id,drone,rule,severity,message,first_ms,last_ms,cleared_ms,acked_by,acked_ms\n",
+            );
+            for a in alerts {
+                out.push_str(&format!(
+                    "{},{},{},{},{},{},{},{},{},{}\n",
+                    a.id,
+                    a.drone,
+                    csv_field(&a.rule),
+                    a.severity.label(),
+                    csv_field(&a.message),
+                    a.first_ms,
+                    a.last_ms,
+                    opt(a.cleared_ms),
+                    csv_field(a.acked_by.as_deref().unwrap_or_default()),
+                    opt(a.acked_ms),
+                ));
+            }
+            out
+        }
+    };
+    fs::write(path, text)
+}
+
+/// Debounce state of one rule for one drone.
+#[derive(Debug, Default, Clone)]
+struct Track {
+    /// Condition true since.
+    since: Option<Instant>,
+    /// Condition false since, while the alert is still active.
+    clear_since: Option<Instant>,
+    /// Active alert raised by this track.
+    alert: Option<u64>,
+}
+
+/// Evaluates rules and keeps the alert history.
+#[derive(Debug, Default, Clone)]
+pub struct AlertEngine {
+    pub rules: Vec<Rule>,
+    alerts: VecDeque<Alert>,
+    tracks: HashMap<(usize, u32), Track>,
+    next_id: u64,
+    /// Wall time of the last evaluation.
+    now_ms: u128,
+}
+
+/// Alerts raised by one evaluation, for notifications.
+#[derive(Debug, Default)]
+pub struct Raised {
+    pub count: usize,
+    pub flash: bool,
+    pub sound: bool,
+    pub worst: Option<Severity>,
+}
+
+impl AlertEngine {
+    pub fn new(rules: Vec<Rule>) -> Self {
+        Self {
+            rules,
+            ..Default::default()
+        }
+    }
+
+    /// History, oldest first.
+    pub fn alerts(&self) -> &VecDeque<Alert> {
+        &self.alerts
+    }
+
+    /// Wall time of the last evaluation, for ages in the UI.
+    pub fn now_ms(&self) -> u128 {
+        self.now_ms
+    }
+
+    /// Active alerts nobody has acknowledged yet.
+    pub fn unacked(&self) -> usize {
+        self.alerts
+            .iter()
+            .filter(|a| a.active() && !a.acked())
+            .count()
+    }
+
+    pub fn active(&self) -> impl Iterator<Item = &Alert> {
+        self.alerts.iter().filter(|a| a.active())
+    }
+
+    pub fn acknowledge(&mut self, id: u64, by: &str) {
+        let now_ms = self.now_ms;
+        if let Some(a) = self.alerts.iter_mut().find(|a| a.id == id && !a.acked()) {
+            a.acked_by = Some(by.to_string());
+            a.acked_ms = Some(now_ms);
+        }
+    }
+
+    pub fn acknowledge_all(&mut self, by: &str) {
+        let ids: Vec<u64> = self
+            .alerts
+            .iter()
+            .filter(|a| !a.acked())
+            .map(|a| a.id)
+            .collect();
+        for id in ids {
+            self.acknowledge(id, by);
+        }
+    }
+
+    fn alert_mut(&mut self, id: u64) -> Option<&mut Alert> {
+        self.alerts.iter_mut().find(|a| a.id == id)
+    }
+
+    /// Run every rule against every drone. `now` is the state's clock and `now_ms`
+    /// the matching wall time.
+    pub fn evaluate(&mut self, state: &AppState, now: Instant, now_ms: u128) -> Raised {
+        self.now_ms = now_ms;
+        let mut raised = Raised::default();
+
+        for i in 0..self.rules.len() {
+            for (&id, d) in &state.drones {
+                let rule = &self.rules[i];
+                if rule.drones.as_ref().is_some_and(|ids| !ids.contains(&id)) {
+                    continue;
+                }
+                let verdict = rule.condition.check(d, now);
+                let mut track = self.tracks.remove(&(i, id)).unwrap_or_default();
+                match verdict {
+                    Some(message) => {
+                        track.clear_since = None;
+                        let since = *track.since.get_or_insert(now);
+                        if let Some(alert) = track.alert.and_then(|a| self.alert_mut(a)) {
+                            alert.last_ms = now_ms;
+                            alert.message = message;
+                        } else if now.saturating_duration_since(since).as_secs_f32()
+                            >= self.rules[i].for_s
+                        {
+                            let rule = &self.rules[i];
+                            raised.count += 1;
+                            raised.flash |= rule.flash;
+                            raised.sound |= rule.sound;
+                            raised.worst = raised.worst.max(Some(rule.severity));
+                            self.next_id += 1;
+                            self.alerts.push_back(Alert {
+                                id: self.next_id,
+                                drone: id,
+                                rule: rule.name.clone(),
+                                severity: rule.severity,
+                                message,
+                                first_ms: now_ms,
+                                last_ms: now_ms,
+                                cleared_ms: None,
+                                acked_by: None,
+                                acked_ms: None,
+                            });
+                            track.alert = Some(self.next_id);
+                        }
+                    }
+                    None => {
+                        track.since = None;
+                        if let Some(alert_id) = track.alert {
+                            let clear_since = *track.clear_since.get_or_insert(now);
+                            if now.saturating_duration_since(clear_since).as_secs_f32()
+                                >= self.rules[i].clear_after_s
+                            {
+                                if let Some(alert) = self.alert_mut(alert_id) {
+                                    alert.cleared_ms = Some(now_ms);
+                                }
+                                track.alert = None;
+                                track.clear_since = None;
+                            }
+                        }
+                    }
+                }
+                self.tracks.insert((i, id), track);
+            }
+        }
+
+        // Drones that disappeared from the state can't violate anything any more
+        let gone: Vec<(usize, u32)> = self
+            .tracks
+            .keys()
+            .filter(|(_, id)| !state.drones.contains_key(id))
+            .copied()
+            .collect();
+        for key in gone {
+            if let Some(alert_id) = self.tracks.remove(&key).and_then(|t| t.alert) {
+                if let Some(alert) = self.alert_mut(alert_id) {
+                    alert.cleared_ms = Some(now_ms);
+                }
+            }
+        }
+
+        // Age out cleared alerts first, oldest first
+        while self.alerts.len() > ALERTS_MAX {
+            match self.alerts.iter().position(|a| !a.active()) {
+                Some(i) => {
+                    self.alerts.remove(i);
+                }
+                None => break,
+            }
+        }
+
+        raised
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::telemetry::Telemetry;
+    use std::time::Duration;
+
+    fn state(battery: f32, now: Instant) -> AppState {
+        let t = Telemetry {
+            id: 1,
+            battery,
+            status: "OK".into(),
+            ts_ms: 1,
+            ..Default::default()
+        };
+        let mut state = AppState::default();
+        state.drones.insert(1, DroneState::new(&t, now));
+        state
+    }
+
+    fn engine() -> AlertEngine {
+        AlertEngine::new(vec![Rule {
+            for_s: 2.0,
+            clear_after_s: 3.0,
+            ..Rule::new("Low battery", Severity::Critical, Condition::BatteryBelow { pct: 20.0 })
+        }])
+    }
+
+    #[test]
+    fn raise_waits_for_debounce() {
+        let t0 = Instant::now();
+        let at = |s: u64| t0 + Duration::from_secs(s);
+        let mut alerts = engine();
+        assert_eq!(alerts.evaluate(&state(10.0, at(0)), at(0), 0).count, 0);
+        assert_eq!(alerts.evaluate(&state(10.0, at(1)), at(1), 1000).count, 0);
+        // A blip back above the threshold restarts the debounce
+        alerts.evaluate(&state(50.0, at(2)), at(2), 2000);
+        assert_eq!(alerts.evaluate(&state(10.0, at(3)), at(3), 3000).count, 0);
+        let raised = alerts.evaluate(&state(10.0, at(5)), at(5), 5000);
+        assert_eq!(raised.count, 1);
+        assert_eq!(raised.worst, Some(Severity::Critical));
+        assert!(raised.flash);
+        assert_eq!(alerts.active().count(), 1);
+        // Still violating: the same alert is updated, not raised again
+        assert_eq!(alerts.evaluate(&state(9.0, at(6)), at(6), 6000).count, 0);
+        assert_eq!(alerts.alerts().len(), 1);
+        assert_eq!(alerts.alerts()[0].last_ms, 6000);
+    }
+
+    #[test]
+    fn clear_waits_for_hysteresis() {
+        let t0 = Instant::now();
+        let at = |s: u64| t0 + Duration::from_secs(s);
+        let mut alerts = engine();
+        alerts.evaluate(&state(10.0, at(0)), at(0), 0);
+        alerts.evaluate(&state(10.0, at(2)), at(2), 2000);
+        assert_eq!(alerts.active().count(), 1);
+
+        alerts.evaluate(&state(50.0, at(3)), at(3), 3000);
+        alerts.evaluate(&state(50.0, at(5)), at(5), 5000);
+        assert_eq!(alerts.active().count(), 1);
+        // Violating again restarts the clear timer
+        alerts.evaluate(&state(10.0, at(6)), at(6), 6000);
+        alerts.evaluate(&state(50.0, at(7)), at(7), 7000);
+        alerts.evaluate(&state(50.0, at(9)), at(9), 9000);
+        assert_eq!(alerts.active().count(), 1);
+        alerts.evaluate(&state(50.0, at(10)), at(10), 10_000);
+        assert_eq!(alerts.active().count(), 0);
+        assert_eq!(alerts.alerts()[0].cleared_ms, Some(10_000));
+        // Cleared alerts stay in the history
+        assert_eq!(alerts.alerts().len(), 1);
+    }
+
+    #[test]
+    fn acknowledge_marks_once() {
+        let t0 = Instant::now();
+        let mut alerts = engine();
+        alerts.evaluate(&state(10.0, t0), t0, 0);
+        let later = t0 + Duration::from_secs(2);
+        alerts.evaluate(&state(10.0, later), later, 2000);
+        assert_eq!(alerts.unacked(), 1);
+        let id = alerts.alerts()[0].id;
+
+        alerts.acknowledge(id, "ops");
+        assert_eq!(alerts.unacked(), 0);
+        assert_eq!(alerts.alerts()[0].acked_by.as_deref(), Some("ops"));
+        assert_eq!(alerts.alerts()[0].acked_ms, Some(2000));
+        // The first acknowledgement sticks
+        alerts.acknowledge(id, "someone else");
+        assert_eq!(alerts.alerts()[0].acked_by.as_deref(), Some("ops"));
+        // Acknowledged alerts stay active until the condition clears
+        assert_eq!(alerts.active().count(), 1);
+    }
+
+    #[test]
+    fn export_quotes_csv_and_writes_parsable_json() {
+        let alerts = [Alert {
+            id: 3,
+            drone: 7,
+            rule: "Low, \"very\" low\nbattery".into(),
+            severity: Severity::Critical,
+            message: "battery 9% < 20%".into(),
+            first_ms: 1000,
+            last_ms: 2000,
+            cleared_ms: None,
+            acked_by: Some("ops".into()),
+            acked_ms: Some(1500),
+        }];
+        let dir = std::env::temp_dir();
+        let csv = dir.join(format!("alerts-{}.csv", std::process::id()));
+        let json = dir.join(format!("alerts-{}.json", std::process::id()));
+
+        export_alerts(&csv, &alerts, ExportFormat::Csv).unwrap();
+        let text = fs::read_to_string(&csv).unwrap();
+        let (header, row) = text.split_once('\n').unwrap();
+        assert_eq!(header.split(',').count(), 10);
+        assert_eq!(
+            row,
+            "3,7,\"Low, \"\"very\"\" low\nbattery\",critical,battery 9% < 20%,1000,2000,,ops,1500\n"
+        );
+
+        export_alerts(&json, &alerts, ExportFormat::Json).unwrap();
+        let parsed: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&json).unwrap()).unwrap();
+        let a = &parsed[0];
+        assert_eq!(a["rule"], alerts[0].rule.as_str());
+        assert_eq!(a["severity"], "critical");
+        assert_eq!((a["id"].as_u64(), a["acked_ms"].as_u64()), (Some(3), Some(1500)));
+        assert!(a["cleared_ms"].is_null());
+
+        fs::remove_file(&csv).unwrap();
+        fs::remove_file(&json).unwrap();
+    }
+}
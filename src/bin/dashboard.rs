@@ -1,4 +1,5 @@
 use clap::Parser;
+use egui_plot::{Line, Plot, PlotPoints};
 use eframe::{
     egui,
     egui::{
@@ -6,66 +7,198 @@ use eframe::{
         TextStyle, Vec2,
     },
 };
-use serde::Deserialize;
 use std::{
-    collections::{HashMap, VecDeque},
+    collections::{BTreeSet, HashMap},
     net::UdpSocket,
+    path::{Path, PathBuf},
     sync::{Arc, Mutex},
     thread,
     time::{Duration, Instant},
 };
+use telemetry_fusion_dashboard::{
+    alerts::{default_rules, export_alerts, load_rules, Alert, AlertEngine, ExportFormat, Severity},
+    fusion::{
+        AppState, Arrival, Bounds, DroneState, FusionPolicy, Lifecycle, LifecycleConfig, Sample,
+        SourceId,
+    },
+    geo::{Geodetic, LocalFrame},
+    geofence::{load_fences, save_fences, FenceKind, FenceShape, Geofence},
+    record::LogWriter,
+    replay::{Replay, SPEEDS},
+    telemetry::{self, now_ms, LOW_BATTERY_PCT},
+    tiles::{tile_xy, tiles_per_side, zoom_for, TileError, TileId, TileSource},
+    track::{Estimator, TrackConfig},
+};
 
 #[derive(Parser, Debug)]
 #[command(name = "dashboard", about = "Telemetry Fusion Dashboard (UDP listener + egui)")]
 struct Args {
-    /// UDP bind address for listening
-    #[arg(short, long, default_value = "127.0.0.1:5000")]
-    bind: String,
+    /// UDP bind address for listening; repeat it to listen for several receivers.
+    /// `=WEIGHT` sets how much a receiver's positions count when fusing (default 1)
+    #[arg(short, long, value_name = "ADDR[=WEIGHT]", default_value = "127.0.0.1:5000")]
+    bind: Vec<BindSpec>,
+
+    /// How positions of a drone heard through several receivers are combined
+    #[arg(long, value_enum, default_value_t = FusionPolicy::Best)]
+    fusion: FusionPolicy,
+
+    /// Position estimator: a constant-velocity Kalman filter, or the per-packet EMA
+    #[arg(long, value_enum, default_value_t = Estimator::Kalman)]
+    estimator: Estimator,
+
+    /// Kalman process noise: standard deviation of unmodelled acceleration, m/s²
+    #[arg(long, value_name = "M/S2", default_value_t = 2.0)]
+    accel_noise: f32,
+
+    /// Kalman measurement noise: standard deviation of reported positions, m
+    #[arg(long, value_name = "M", default_value_t = 1.5)]
+    position_noise: f32,
+
+    /// How far to dead-reckon a silent drone from its last velocity; 0 disables
+    #[arg(long, value_name = "SECS", default_value_t = 5.0)]
+    predict_horizon: f32,
+
+    /// Fixed initial map view: +/- this many world units around the origin.
+    /// Without it the map starts in fit-all mode and follows the fleet.
+    #[arg(long)]
+    world_extent: Option<f32>,
+
+    /// Home point for geodetic packets, as LAT,LON[,ALT]. Lat/lon telemetry is drawn
+    /// in east/north metres from here; defaults to the first geodetic packet received.
+    #[arg(long, value_name = "LAT,LON[,ALT]", allow_hyphen_values = true)]
+    home: Option<Geodetic>,
+
+    /// Offline map tiles: a {z}/{x}/{y}.png directory or an .mbtiles file
+    #[arg(long, value_name = "PATH")]
+    tiles: Option<PathBuf>,
+
+    /// Geofence zones (JSON); zones drawn on the map are saved back here
+    #[arg(long, value_name = "PATH")]
+    geofences: Option<PathBuf>,
+
+    /// Alert rules (JSON); without it a built-in set covers battery, stale links,
+    /// status and geofences
+    #[arg(long, value_name = "PATH")]
+    alerts: Option<PathBuf>,
+
+    /// Name recorded when acknowledging alerts (defaults to $USER)
+    #[arg(long, value_name = "NAME")]
+    operator: Option<String>,
+
+    /// Seconds without a packet before a drone counts as stale
+    #[arg(long, value_name = "SECS", default_value_t = 2.0)]
+    stale_after: f32,
+
+    /// Seconds without a packet before a drone counts as lost
+    #[arg(long, value_name = "SECS", default_value_t = 10.0)]
+    lost_after: f32,
+
+    /// Remove lost drones instead of keeping a ghost at their last position
+    #[arg(long)]
+    remove_lost: bool,
+
+    /// Record every received packet to this session log (appends if it exists)
+    #[arg(long, value_name = "PATH")]
+    record: Option<PathBuf>,
+
+    /// Replay a recorded session log instead of listening on UDP
+    #[arg(long, value_name = "PATH", conflicts_with = "record")]
+    replay: Option<PathBuf>,
+}
 
-    /// World coordinate extent (+/- this many units on both axes)
-    #[arg(long, default_value_t = 120.0)]
-    world_extent: f32,
+/// One `--bind` listener and the weight of its positions.
+#[derive(Debug, Clone)]
+struct BindSpec {
+    addr: String,
+    weight: f32,
 }
 
-#[derive(Debug, Deserialize, Clone)]
-struct Telemetry {
-    id: u32,
-    x: f32,
-    y: f32,
-    z: f32,
-    battery: f32,
-    status: String,
-    ts_ms: u128,
+impl std::str::FromStr for BindSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.rsplit_once('=') {
+            Some((addr, w)) => {
+                let weight: f32 = w.trim().parse().map_err(|e| format!("weight {w:?}: {e}"))?;
+                if !(weight >= 0.0 && weight.is_finite()) {
+                    return Err(format!("weight {w:?} must be a non-negative number"));
+                }
+                Ok(Self {
+                    addr: addr.to_string(),
+                    weight,
+                })
+            }
+            None => Ok(Self {
+                addr: s.to_string(),
+                weight: 1.0,
+            }),
+        }
+    }
 }
 
-#[derive(Debug, Clone)]
-struct DroneState {
-    x: f32,
-    y: f32,
-    z: f32,
-    battery: f32,
-    status: String,
-    last_ts_ms: u128,
-    last_seen: Instant,
-
-    // Visual smoothing / trails
-    smoothed_x: f32,
-    smoothed_y: f32,
-    // (x, y, when recorded)
-    trail: VecDeque<(f32, f32, Instant)>,
+/// Initial map half-span when fitting to the fleet (before any packet arrives).
+const DEFAULT_WORLD_EXTENT: f32 = 120.0;
+
+/// How long the window edge flashes when an alert is raised.
+const ALERT_FLASH: Duration = Duration::from_millis(1500);
+
+/// Rows listed in the alerts panel; exports include every matching alert.
+const ALERTS_SHOWN: usize = 200;
+
+/// Session recording, shared between the top-bar toggle and the listener thread.
+#[derive(Default)]
+struct Recording {
+    path: Option<PathBuf>,
+    writer: Option<LogWriter>,
+    error: Option<String>,
 }
 
+impl Recording {
+    fn start(&mut self) {
+        let path = self
+            .path
+            .get_or_insert_with(|| PathBuf::from(format!("session-{}.tflog", now_ms())))
+            .clone();
+        match LogWriter::open(&path) {
+            Ok(w) => {
+                println!("dashboard: recording to {}", path.display());
+                self.writer = Some(w);
+                self.error = None;
+            }
+            Err(e) => self.error = Some(format!("{}: {e}", path.display())),
+        }
+    }
+
+    fn stop(&mut self) {
+        self.writer = None;
+    }
+}
+
+/// Which alerts the alerts panel lists and exports.
 #[derive(Default)]
-struct AppState {
-    drones: HashMap<u32, DroneState>,
-    total_packets: u64,
-    last_packet_at: Option<Instant>,
+struct AlertFilter {
+    drone: Option<u32>,
+    min_severity: Severity,
+    active_only: bool,
+}
+
+impl AlertFilter {
+    fn matches(&self, a: &Alert) -> bool {
+        self.drone.is_none_or(|id| id == a.drone)
+            && a.severity >= self.min_severity
+            && (!self.active_only || a.active())
+    }
 }
 
 struct App {
     state: Arc<Mutex<AppState>>,
-    world_extent: f32,
+    recording: Arc<Mutex<Recording>>,
+    replay: Option<Replay>,
+    camera: Camera,
+    tiles: Option<TileLayer>,
     show_trails: bool,
+    show_uncertainty: bool,
+    show_predictions: bool,
     styled_once: bool,
     selected: Option<u32>,
 
@@ -73,96 +206,814 @@ struct App {
     hud_open: bool,   // desired (target) state
     hud_t: f32,       // animation progress 0..1
     hud_expanded: bool,
+
+    // Geofences window: settings for the next zone, the one being drawn, and
+    // where Save writes
+    show_fences: bool,
+    fence_template: FenceDraft,
+    fence_draft: Option<FenceDraft>,
+    fences_path: PathBuf,
+    fences_status: Option<String>,
+
+    // Alerts panel (docked right or floating), who acknowledges, and the flash
+    // of the latest raised alert
+    show_alerts: bool,
+    alerts_docked: bool,
+    show_diagnostics: bool,
+    alert_filter: AlertFilter,
+    alerts_status: Option<String>,
+    operator: String,
+    flash: Option<(Instant, Severity)>,
+
+    // Fleet table: sort column and direction, text filter, multi-selection (always
+    // containing `selected`) and the anchor of shift-click ranges
+    show_fleet: bool,
+    fleet_sort: FleetColumn,
+    fleet_desc: bool,
+    fleet_query: String,
+    fleet_selection: BTreeSet<u32>,
+    fleet_anchor: Option<u32>,
+    labels_selected_only: bool,
+
+    // History charts in the details sheet; every drone in `fleet_selection` is overlaid
+    chart_window: ChartWindow,
 }
 
 impl App {
-    fn new(state: Arc<Mutex<AppState>>, world_extent: f32) -> Self {
+    fn new(
+        state: Arc<Mutex<AppState>>,
+        recording: Arc<Mutex<Recording>>,
+        replay: Option<Replay>,
+        world_extent: Option<f32>,
+        tiles: Option<TileLayer>,
+        fences_path: Option<PathBuf>,
+        operator: String,
+    ) -> Self {
         Self {
             state,
-            world_extent,
+            recording,
+            replay,
+            camera: match world_extent {
+                Some(extent) => Camera::new(extent, CameraMode::Free),
+                None => Camera::new(DEFAULT_WORLD_EXTENT, CameraMode::FitAll),
+            },
+            tiles,
             show_trails: true,
+            show_uncertainty: true,
+            show_predictions: true,
             styled_once: false,
             selected: None,
             hud_open: false,
             hud_t: 0.0,
             hud_expanded: false,
+            show_fences: false,
+            fence_template: FenceDraft::default(),
+            fence_draft: None,
+            fences_path: fences_path.unwrap_or_else(|| PathBuf::from("geofences.json")),
+            fences_status: None,
+            show_alerts: false,
+            show_diagnostics: false,
+            alerts_docked: true,
+            alert_filter: AlertFilter::default(),
+            alerts_status: None,
+            operator,
+            flash: None,
+            show_fleet: false,
+            fleet_sort: FleetColumn::Id,
+            fleet_desc: false,
+            fleet_query: String::new(),
+            fleet_selection: BTreeSet::new(),
+            fleet_anchor: None,
+            labels_selected_only: false,
+            chart_window: ChartWindow::Minute,
         }
     }
 }
 
-/* ------------------------------ UDP listener ------------------------------ */
+/* ------------------------------ Alerts panel ------------------------------ */
 
-fn spawn_udp_listener(bind: String, shared: Arc<Mutex<AppState>>) {
-    thread::spawn(move || {
-        let socket = UdpSocket::bind(&bind).expect("failed to bind UDP socket");
-        socket
-            .set_nonblocking(true)
-            .expect("failed to set non-blocking");
+impl App {
+    /// Alert history with filters, acknowledgement and export; the same body is
+    /// used docked and floating.
+    fn alerts_ui(&mut self, ui: &mut egui::Ui) {
+        let shared = self.state.clone();
+        let mut state = shared.lock().unwrap();
+        let now_ms = state.alerts.now_ms();
+
+        ui.horizontal(|ui| {
+            ui.label(
+                RichText::new(format!(
+                    "{} active, {} unacknowledged · {} rules",
+                    state.alerts.active().count(),
+                    state.alerts.unacked(),
+                    state.alerts.rules.len()
+                ))
+                .small(),
+            );
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                let (icon, hint) = if self.alerts_docked {
+                    ("⇱", "Undock")
+                } else {
+                    ("⇲", "Dock to the side")
+                };
+                if ui.small_button(icon).on_hover_text(hint).clicked() {
+                    self.alerts_docked = !self.alerts_docked;
+                }
+            });
+        });
 
-        println!("dashboard: listening on {}", bind);
+        // Filters
+        let filter = &mut self.alert_filter;
+        ui.horizontal(|ui| {
+            let mut drones: Vec<u32> = state.alerts.alerts().iter().map(|a| a.drone).collect();
+            drones.sort_unstable();
+            drones.dedup();
+            egui::ComboBox::from_id_source("alert_drone")
+                .selected_text(match filter.drone {
+                    Some(id) => format!("Drone #{id}"),
+                    None => "All drones".to_string(),
+                })
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut filter.drone, None, "All drones");
+                    for id in drones {
+                        ui.selectable_value(&mut filter.drone, Some(id), format!("Drone #{id}"));
+                    }
+                });
+            egui::ComboBox::from_id_source("alert_severity")
+                .selected_text(format!("≥ {}", filter.min_severity.label()))
+                .show_ui(ui, |ui| {
+                    for s in [Severity::Info, Severity::Warning, Severity::Critical] {
+                        let label = format!("≥ {}", s.label());
+                        ui.selectable_value(&mut filter.min_severity, s, label);
+                    }
+                });
+            ui.checkbox(&mut filter.active_only, "Active only");
+        });
 
-        let mut buf = [0u8; 2048];
+        // Newest first
+        let shown: Vec<Alert> = state
+            .alerts
+            .alerts()
+            .iter()
+            .rev()
+            .filter(|a| filter.matches(a))
+            .cloned()
+            .collect();
+
+        ui.horizontal(|ui| {
+            let unacked: Vec<u64> = shown.iter().filter(|a| !a.acked()).map(|a| a.id).collect();
+            if ui
+                .add_enabled(!unacked.is_empty(), egui::Button::new("Ack shown"))
+                .clicked()
+            {
+                for id in unacked {
+                    state.alerts.acknowledge(id, &self.operator);
+                }
+            }
+            for format in [ExportFormat::Csv, ExportFormat::Json] {
+                let label = format!("Export {}", format.extension().to_uppercase());
+                if ui.add_enabled(!shown.is_empty(), egui::Button::new(label)).clicked() {
+                    let path = PathBuf::from(format!("alerts-{}.{}", now_ms, format.extension()));
+                    self.alerts_status = Some(match export_alerts(&path, &shown, format) {
+                        Ok(()) => format!("Exported {} alerts to {}", shown.len(), path.display()),
+                        Err(e) => format!("{}: {e}", path.display()),
+                    });
+                }
+            }
+        });
+        if let Some(status) = &self.alerts_status {
+            ui.label(RichText::new(status).small());
+        }
+        ui.separator();
 
-        loop {
-            match socket.recv_from(&mut buf) {
-                Ok((n, _addr)) => {
-                    if let Ok(msg) = std::str::from_utf8(&buf[..n]) {
-                        if let Ok(t) = serde_json::from_str::<Telemetry>(msg) {
-                            let mut guard = shared.lock().unwrap();
-
-                            // Insert or get the drone
-                            let entry = guard.drones.entry(t.id).or_insert(DroneState {
-                                x: t.x,
-                                y: t.y,
-                                z: t.z,
-                                battery: t.battery,
-                                status: t.status.clone(),
-                                last_ts_ms: t.ts_ms,
-                                last_seen: Instant::now(),
-                                smoothed_x: t.x,
-                                smoothed_y: t.y,
-                                trail: VecDeque::with_capacity(128),
-                            });
+        if shown.is_empty() {
+            ui.label(RichText::new("No alerts match.").small());
+            return;
+        }
+        if shown.len() > ALERTS_SHOWN {
+            ui.label(RichText::new(format!("Newest {ALERTS_SHOWN} of {}", shown.len())).small());
+        }
+
+        // Clicking a drone id selects it and centers the map on it
+        let mut ack = None;
+        let mut focus = None;
+        egui::ScrollArea::both().auto_shrink([false, true]).show(ui, |ui| {
+            egui::Grid::new("alert_history")
+                .striped(true)
+                .num_columns(8)
+                .show(ui, |ui| {
+                    let header =
+                        ["", "Drone", "Rule", "Message", "First (UTC)", "Last", "Ack", "Cleared"];
+                    for h in header {
+                        ui.label(RichText::new(h).small().strong());
+                    }
+                    ui.end_row();
 
-                            // Update latest raw values
-                            entry.x = t.x;
-                            entry.y = t.y;
-                            entry.z = t.z;
-                            entry.battery = t.battery;
-                            entry.status = t.status;
-                            entry.last_ts_ms = t.ts_ms;
-                            entry.last_seen = Instant::now();
-
-                            // EMA smoothing for visual position
-                            let alpha = 0.25_f32; // lower = smoother, higher = snappier
-                            entry.smoothed_x =
-                                entry.smoothed_x + alpha * (entry.x - entry.smoothed_x);
-                            entry.smoothed_y =
-                                entry.smoothed_y + alpha * (entry.y - entry.smoothed_y);
-
-                            // Record trail using smoothed coords
-                            entry
-                                .trail
-                                .push_back((entry.smoothed_x, entry.smoothed_y, Instant::now()));
-
-                            // Prune trail by size and age (keep a long history)
-                            const TRAIL_MAX_POINTS: usize = 600;
-                            const TRAIL_MAX_AGE: Duration = Duration::from_secs(20);
-                            while entry.trail.len() > TRAIL_MAX_POINTS {
-                                entry.trail.pop_front();
+                    for a in shown.iter().take(ALERTS_SHOWN) {
+                        let color = if a.active() {
+                            severity_color(a.severity)
+                        } else {
+                            Color32::from_rgb(150, 160, 175)
+                        };
+                        ui.label(RichText::new("●").color(severity_color(a.severity)))
+                            .on_hover_text(a.severity.label());
+                        let selected = self.selected == Some(a.drone);
+                        if ui.selectable_label(selected, format!("#{}", a.drone)).clicked() {
+                            focus = Some(a.drone);
+                        }
+                        ui.label(RichText::new(&a.rule).color(color));
+                        ui.label(RichText::new(&a.message).small().color(color));
+                        let age = format_age(now_ms.saturating_sub(a.first_ms));
+                        ui.label(RichText::new(format_clock(a.first_ms)).monospace().small())
+                            .on_hover_text(format!("{age} ago"));
+                        ui.label(RichText::new(format_clock(a.last_ms)).monospace().small());
+                        match &a.acked_by {
+                            Some(by) => {
+                                let at = a.acked_ms.map(format_clock).unwrap_or_default();
+                                ui.label(RichText::new(by).small()).on_hover_text(at);
                             }
-                            while let Some(&(_, _, when)) = entry.trail.front() {
-                                if when.elapsed() > TRAIL_MAX_AGE {
-                                    entry.trail.pop_front();
-                                } else {
-                                    break;
+                            None => {
+                                if ui.small_button("Ack").clicked() {
+                                    ack = Some(a.id);
                                 }
                             }
+                        }
+                        match a.cleared_ms {
+                            Some(c) => ui.label(RichText::new(format_clock(c)).monospace().small()),
+                            None => ui.label(RichText::new("active").small().color(color)),
+                        };
+                        ui.end_row();
+                    }
+                });
+        });
+
+        if let Some(id) = ack {
+            state.alerts.acknowledge(id, &self.operator);
+        }
+        if let Some(id) = focus {
+            if let Some(d) = state.drones.get(&id) {
+                self.camera.center = Vec2::new(d.smoothed_x, d.smoothed_y);
+                self.camera.set_mode(CameraMode::Follow);
+            }
+            self.selected = Some(id);
+            self.hud_open = true;
+        }
+    }
+}
+
+/* ------------------------------- Fleet table ------------------------------- */
+
+/// Sortable columns of the fleet table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FleetColumn {
+    Id,
+    State,
+    Status,
+    Battery,
+    Altitude,
+    Speed,
+    Age,
+    Link,
+}
+
+impl FleetColumn {
+    const ALL: [FleetColumn; 8] = [
+        FleetColumn::Id,
+        FleetColumn::State,
+        FleetColumn::Status,
+        FleetColumn::Battery,
+        FleetColumn::Altitude,
+        FleetColumn::Speed,
+        FleetColumn::Age,
+        FleetColumn::Link,
+    ];
+
+    fn title(self) -> &'static str {
+        match self {
+            FleetColumn::Id => "ID",
+            FleetColumn::State => "State",
+            FleetColumn::Status => "Status",
+            FleetColumn::Battery => "Battery",
+            FleetColumn::Altitude => "Alt",
+            FleetColumn::Speed => "Speed",
+            FleetColumn::Age => "Last pkt",
+            FleetColumn::Link => "Link",
+        }
+    }
+
+    fn compare(self, a: &(u32, &DroneState), b: &(u32, &DroneState)) -> std::cmp::Ordering {
+        let (da, db) = (a.1, b.1);
+        match self {
+            FleetColumn::Id => a.0.cmp(&b.0),
+            FleetColumn::State => da.lifecycle.cmp(&db.lifecycle),
+            FleetColumn::Status => da.status.cmp(&db.status),
+            FleetColumn::Battery => da.battery.total_cmp(&db.battery),
+            FleetColumn::Altitude => da.z.total_cmp(&db.z),
+            // Drones without a speed sort below any reported one
+            FleetColumn::Speed => da
+                .speed()
+                .unwrap_or(f32::NEG_INFINITY)
+                .total_cmp(&db.speed().unwrap_or(f32::NEG_INFINITY)),
+            // Most recently heard first when ascending
+            FleetColumn::Age => db.last_seen.cmp(&da.last_seen),
+            FleetColumn::Link => da.link.quality().total_cmp(&db.link.quality()),
+        }
+        .then(a.0.cmp(&b.0))
+    }
+}
+
+impl App {
+    /// Every drone with sortable columns; click selects (synced with the map and
+    /// HUD), Ctrl-click toggles and Shift-click extends for the bulk actions.
+    fn fleet_ui(&mut self, ui: &mut egui::Ui) {
+        let shared = self.state.clone();
+        let mut state = shared.lock().unwrap();
+        let now = state.now();
+
+        ui.horizontal(|ui| {
+            ui.label("Filter");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.fleet_query)
+                    .hint_text("id or status")
+                    .desired_width(140.0),
+            );
+            ui.checkbox(&mut self.labels_selected_only, "Map labels for selection only");
+            let lost: Vec<u32> = state
+                .drones
+                .iter()
+                .filter(|(_, d)| d.lifecycle == Lifecycle::Lost)
+                .map(|(id, _)| *id)
+                .collect();
+            let remove_lost = egui::Button::new(format!("Remove lost ({})", lost.len()));
+            if ui
+                .add_enabled(!lost.is_empty(), remove_lost)
+                .on_hover_text("Drop ghost markers of lost drones")
+                .clicked()
+            {
+                for id in lost {
+                    state.forget(id);
+                }
+            }
+        });
+
+        let query = self.fleet_query.trim().to_lowercase();
+        let mut rows: Vec<(u32, &DroneState)> = state
+            .drones
+            .iter()
+            .map(|(id, d)| (*id, d))
+            .filter(|(id, d)| {
+                query.is_empty()
+                    || id.to_string().contains(query.trim_start_matches('#'))
+                    || d.status.to_lowercase().contains(&query)
+            })
+            .collect();
+        rows.sort_by(|a, b| self.fleet_sort.compare(a, b));
+        if self.fleet_desc {
+            rows.reverse();
+        }
+        let order: Vec<u32> = rows.iter().map(|(id, _)| *id).collect();
+
+        // Bulk actions on the selection
+        let picked: Vec<u32> = self.fleet_selection.iter().copied().collect();
+        let mut fit = None;
+        let mut forget = false;
+        let mut ack = false;
+        ui.horizontal(|ui| {
+            let total = state.drones.len();
+            let summary = format!("{} of {total} · {} selected", rows.len(), picked.len());
+            ui.label(RichText::new(summary).small());
+            ui.add_enabled_ui(!picked.is_empty(), |ui| {
+                if ui.button("Fit map").on_hover_text("Frame the selected drones").clicked() {
+                    let mut bounds: Option<Bounds> = None;
+                    for d in picked.iter().filter_map(|id| state.drones.get(id)) {
+                        match bounds.as_mut() {
+                            Some(b) => b.include(d.smoothed_x, d.smoothed_y),
+                            None => bounds = Some(Bounds::point(d.smoothed_x, d.smoothed_y)),
+                        }
+                    }
+                    fit = bounds;
+                }
+                if ui.button("Ack alerts").on_hover_text("Acknowledge their alerts").clicked() {
+                    ack = true;
+                }
+                if ui
+                    .button("Forget")
+                    .on_hover_text("Drop them until they send again")
+                    .clicked()
+                {
+                    forget = true;
+                }
+                if ui.button("Clear").clicked() {
+                    self.selected = None;
+                }
+            });
+        });
+
+        // Lifecycle transitions, newest first
+        egui::CollapsingHeader::new(format!("Transitions ({})", state.lifecycle_events.len()))
+            .id_source("fleet_transitions")
+            .show(ui, |ui| {
+                egui::ScrollArea::vertical()
+                    .id_source("fleet_transitions_scroll")
+                    .max_height(160.0)
+                    .show(ui, |ui| {
+                        for e in state.lifecycle_events.iter().rev() {
+                            let age = now.saturating_duration_since(e.at).as_millis();
+                            let text = match e.from {
+                                Some(from) => format!(
+                                    "{:>7} ago  #{:<4} {} → {}{}",
+                                    format_age(age),
+                                    e.drone,
+                                    from.label(),
+                                    e.to.label(),
+                                    if e.removed { " (removed)" } else { "" }
+                                ),
+                                None => format!("{:>7} ago  #{:<4} new", format_age(age), e.drone),
+                            };
+                            ui.label(
+                                RichText::new(text)
+                                    .monospace()
+                                    .small()
+                                    .color(lifecycle_color(e.to)),
+                            );
+                        }
+                    });
+            });
+        ui.separator();
+
+        let mut clicked = None;
+        egui::ScrollArea::vertical().auto_shrink([false, true]).show(ui, |ui| {
+            egui::Grid::new("fleet_table")
+                .striped(true)
+                .num_columns(FleetColumn::ALL.len())
+                .show(ui, |ui| {
+                    // Header: click sorts, clicking again flips the direction
+                    for col in FleetColumn::ALL {
+                        let arrow = match (col == self.fleet_sort, self.fleet_desc) {
+                            (true, false) => " ⏶",
+                            (true, true) => " ⏷",
+                            (false, _) => "",
+                        };
+                        let title =
+                            RichText::new(format!("{}{arrow}", col.title())).small().strong();
+                        if ui.add(egui::Button::new(title).frame(false)).clicked() {
+                            if self.fleet_sort == col {
+                                self.fleet_desc = !self.fleet_desc;
+                            } else {
+                                self.fleet_sort = col;
+                                self.fleet_desc = false;
+                            }
+                        }
+                    }
+                    ui.end_row();
+
+                    for (id, d) in &rows {
+                        let selected = self.fleet_selection.contains(id);
+                        if ui.selectable_label(selected, format!("#{id}")).clicked() {
+                            clicked = Some(*id);
+                        }
+                        ui.label(
+                            RichText::new(d.lifecycle.label())
+                                .small()
+                                .color(lifecycle_color(d.lifecycle)),
+                        );
+                        status_badge(ui, &d.status);
+                        let battery = RichText::new(format!("{:.0}%", d.battery)).monospace();
+                        ui.label(if d.battery < LOW_BATTERY_PCT {
+                            battery.color(Color32::from_rgb(255, 110, 110))
+                        } else {
+                            battery
+                        });
+                        ui.label(RichText::new(format!("{:.1}", d.z)).monospace());
+                        let speed = d.speed().map_or("–".to_string(), |v| format!("{v:.1}"));
+                        ui.label(RichText::new(speed).monospace());
+                        let age_ms = now.saturating_duration_since(d.last_seen).as_millis();
+                        ui.label(RichText::new(format_age(age_ms)).monospace());
+                        let q = d.link.quality();
+                        ui.label(
+                            RichText::new(format!("● {:.0}%", q * 100.0))
+                                .monospace()
+                                .color(link_quality_color(q)),
+                        );
+                        ui.end_row();
+                    }
+                });
+        });
+
+        if let Some(id) = clicked {
+            let (ctrl, shift) = ui.input(|i| (i.modifiers.command, i.modifiers.shift));
+            let anchor = self.fleet_anchor.and_then(|a| order.iter().position(|&o| o == a));
+            let index = order.iter().position(|&o| o == id);
+            match (shift, anchor, index) {
+                (true, Some(a), Some(i)) => {
+                    self.fleet_selection.extend(&order[a.min(i)..=a.max(i)]);
+                    self.selected = Some(id);
+                }
+                _ if ctrl => {
+                    if self.fleet_selection.remove(&id) {
+                        self.selected = self.fleet_selection.iter().next_back().copied();
+                    } else {
+                        self.fleet_selection.insert(id);
+                        self.selected = Some(id);
+                    }
+                    self.fleet_anchor = Some(id);
+                }
+                _ => {
+                    self.fleet_selection = BTreeSet::from([id]);
+                    self.selected = Some(id);
+                    self.fleet_anchor = Some(id);
+                }
+            }
+            self.hud_open = self.selected.is_some();
+        }
+
+        if let Some(bounds) = fit {
+            self.camera.frame(bounds);
+        }
+        if ack {
+            let ids: Vec<u64> = state
+                .alerts
+                .alerts()
+                .iter()
+                .filter(|a| !a.acked() && picked.contains(&a.drone))
+                .map(|a| a.id)
+                .collect();
+            for id in ids {
+                state.alerts.acknowledge(id, &self.operator);
+            }
+        }
+        if forget {
+            for id in &picked {
+                state.forget(*id);
+            }
+            self.selected = None;
+        }
+    }
+}
+
+/* ----------------------------- History charts ----------------------------- */
+
+/// Time span shown by the history charts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ChartWindow {
+    Minute,
+    TenMinutes,
+    Session,
+}
+
+impl ChartWindow {
+    const ALL: [ChartWindow; 3] = [
+        ChartWindow::Minute,
+        ChartWindow::TenMinutes,
+        ChartWindow::Session,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            ChartWindow::Minute => "1 min",
+            ChartWindow::TenMinutes => "10 min",
+            ChartWindow::Session => "Session",
+        }
+    }
+
+    /// `None` for the whole session.
+    fn span(self) -> Option<Duration> {
+        match self {
+            ChartWindow::Minute => Some(Duration::from_secs(60)),
+            ChartWindow::TenMinutes => Some(Duration::from_secs(600)),
+            ChartWindow::Session => None,
+        }
+    }
+}
+
+type ChartValue = fn(&Sample) -> Option<f32>;
+
+const CHARTS: [(&str, ChartValue); 4] = [
+    ("Battery %", |s| Some(s.battery)),
+    ("Altitude", |s| Some(s.z)),
+    ("Speed", |s| s.speed),
+    ("Packet rate (Hz)", |s| Some(s.rate_hz)),
+];
+
+impl App {
+    /// Battery, altitude, speed and packet rate over the chosen window, one line per
+    /// drone in the selection. X is seconds before now.
+    fn history_charts(&mut self, ui: &mut egui::Ui) {
+        let shared = self.state.clone();
+        let state = shared.lock().unwrap();
+        let now = state.now();
+
+        ui.horizontal(|ui| {
+            for w in ChartWindow::ALL {
+                ui.selectable_value(&mut self.chart_window, w, w.label());
+            }
+            ui.separator();
+            let mut others: Vec<u32> = state
+                .drones
+                .keys()
+                .filter(|id| !self.fleet_selection.contains(id))
+                .copied()
+                .collect();
+            others.sort_unstable();
+            egui::ComboBox::from_id_source("chart_overlay")
+                .selected_text("+ Overlay")
+                .show_ui(ui, |ui| {
+                    for id in others {
+                        if ui.selectable_label(false, format!("#{id}")).clicked() {
+                            self.fleet_selection.insert(id);
+                        }
+                    }
+                });
+        });
+
+        // Overlaid drones; all but the selected one can be removed
+        ui.horizontal_wrapped(|ui| {
+            let mut remove = None;
+            for &id in &self.fleet_selection {
+                let (r, g, b) = drone_rgb(id);
+                let text = RichText::new(format!("● #{id}")).color(Color32::from_rgb(r, g, b));
+                if Some(id) == self.selected {
+                    ui.label(text.strong());
+                } else if ui.small_button(text).on_hover_text("Remove from charts").clicked() {
+                    remove = Some(id);
+                }
+            }
+            if let Some(id) = remove {
+                self.fleet_selection.remove(&id);
+            }
+        });
+
+        let span = self.chart_window.span();
+        let series: Vec<(u32, Vec<Sample>)> = self
+            .fleet_selection
+            .iter()
+            .filter_map(|id| {
+                let samples = state
+                    .history
+                    .get(id)?
+                    .samples()
+                    .iter()
+                    .filter(|s| span.is_none_or(|w| now.saturating_duration_since(s.at) <= w))
+                    .copied()
+                    .collect();
+                Some((*id, samples))
+            })
+            .collect();
+        drop(state);
+
+        for (title, value) in CHARTS {
+            ui.label(RichText::new(title).small());
+            let mut plot = Plot::new(("history", title))
+                .height(110.0)
+                .link_axis("history", true, false)
+                .allow_drag(false)
+                .allow_zoom(false)
+                .allow_scroll(false)
+                .allow_boxed_zoom(false)
+                .include_x(0.0);
+            if let Some(w) = span {
+                plot = plot.include_x(-w.as_secs_f64());
+            }
+            plot.show(ui, |plot_ui| {
+                for (id, samples) in &series {
+                    let points: PlotPoints = samples
+                        .iter()
+                        .filter_map(|s| {
+                            let t = now.saturating_duration_since(s.at).as_secs_f64();
+                            Some([-t, value(s)? as f64])
+                        })
+                        .collect();
+                    let (r, g, b) = drone_rgb(*id);
+                    plot_ui.line(
+                        Line::new(points)
+                            .color(Color32::from_rgb(r, g, b))
+                            .name(format!("#{id}")),
+                    );
+                }
+            });
+        }
+    }
+}
+
+/* ------------------------------- Diagnostics ------------------------------- */
+
+impl App {
+    /// Reject counters by reason and by sender, and the latest rejected payloads.
+    fn diagnostics_ui(&mut self, ui: &mut egui::Ui) {
+        let mut state = self.state.lock().unwrap();
+        let diag = &state.diagnostics;
+        let mut clear = false;
+
+        ui.horizontal(|ui| {
+            ui.label(
+                RichText::new(format!(
+                    "{} rejected, {} accepted",
+                    diag.rejected, state.total_packets
+                ))
+                .small(),
+            );
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                clear = ui
+                    .add_enabled(diag.rejected > 0, egui::Button::new("Clear").small())
+                    .clicked();
+            });
+        });
+        ui.separator();
+        if diag.rejected == 0 {
+            ui.label(RichText::new("No rejected datagrams.").small());
+            return;
+        }
+
+        // Counters, most frequent first
+        ui.columns(2, |cols| {
+            let tables = [("Reason", &diag.by_reason), ("Sender", &diag.by_sender)];
+            for (ui, (title, counts)) in cols.iter_mut().zip(tables) {
+                let mut rows: Vec<(&String, &u64)> = counts.iter().collect();
+                rows.sort_by(|a, b| b.1.cmp(a.1));
+                egui::Grid::new(("reject_counts", title))
+                    .striped(true)
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label(RichText::new(title).small().strong());
+                        ui.label(RichText::new("Count").small().strong());
+                        ui.end_row();
+                        for (key, n) in rows {
+                            ui.label(RichText::new(key).small());
+                            ui.label(RichText::new(n.to_string()).monospace().small());
+                            ui.end_row();
+                        }
+                    });
+            }
+        });
+        ui.separator();
+
+        // Latest payloads, newest first; expand one to read or copy it
+        ui.label(RichText::new(format!("Latest {} rejects", diag.recent().len())).small());
+        egui::ScrollArea::vertical().auto_shrink([false, true]).show(ui, |ui| {
+            for r in diag.recent().iter().rev() {
+                let title = format!("{}  {}  {}", format_clock(r.wall_ms), r.sender, r.error);
+                egui::CollapsingHeader::new(RichText::new(title).monospace().small())
+                    .id_source(("reject", r.id))
+                    .show(ui, |ui| {
+                        let preview = r.preview();
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new(format!("{} bytes", r.len)).small());
+                            if ui.small_button("Copy").clicked() {
+                                ui.output_mut(|o| o.copied_text = preview.clone());
+                            }
+                        });
+                        ui.add(Label::new(RichText::new(preview).monospace().small()).wrap(true));
+                    });
+            }
+        });
+
+        if clear {
+            state.diagnostics = Default::default();
+        }
+    }
+}
+
+/* ------------------------------ UDP listener ------------------------------ */
 
-                            guard.total_packets += 1;
-                            guard.last_packet_at = Some(Instant::now());
+/// Bind a non-blocking UDP socket for a listener.
+fn bind_udp(addr: &str) -> std::io::Result<UdpSocket> {
+    let socket = UdpSocket::bind(addr)?;
+    socket.set_nonblocking(true)?;
+    Ok(socket)
+}
+
+fn spawn_udp_listener(
+    socket: UdpSocket,
+    source: SourceId,
+    shared: Arc<Mutex<AppState>>,
+    recording: Arc<Mutex<Recording>>,
+) {
+    thread::spawn(move || {
+        let mut buf = [0u8; 2048];
+
+        loop {
+            match socket.recv_from(&mut buf) {
+                Ok((n, addr)) => {
+                    // Persist the raw datagram before decoding so rejects are kept too
+                    {
+                        let mut rec = recording.lock().unwrap();
+                        if let Some(w) = rec.writer.as_mut() {
+                            if let Err(e) = w.append(now_ms() as u64, addr, &buf[..n]) {
+                                rec.error = Some(format!("write failed: {e}"));
+                                rec.writer = None;
+                            }
                         }
                     }
+
+                    match telemetry::decode(&buf[..n]) {
+                        Ok(t) => shared.lock().unwrap().apply(t, Arrival::now(source)),
+                        Err(e) => shared.lock().unwrap().diagnostics.record(
+                            &addr.to_string(),
+                            e,
+                            &buf[..n],
+                            now_ms(),
+                        ),
+                    }
                 }
                 Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
                     // No data right now; avoid busy spin
@@ -176,8 +1027,338 @@ fn spawn_udp_listener(bind: String, shared: Arc<Mutex<AppState>>) {
     });
 }
 
+/* -------------------------------- Camera -------------------------------- */
+
+/// How the map camera moves on its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CameraMode {
+    /// Only user pan/zoom moves the view.
+    Free,
+    /// Keep every drone in view.
+    FitAll,
+    /// Keep the selected drone centered.
+    Follow,
+}
+
+// Auto-fit: headroom added when re-framing, how far content may shrink before
+// zooming back in, and the per-frame easing factor
+const FIT_MARGIN: f32 = 1.3;
+const FIT_SHRINK_RATIO: f32 = 0.45;
+const FIT_EASE: f32 = 0.15;
+
+/// Map view: world point at the canvas center plus zoom level.
+///
+/// World +y is screen up. Zoom is stored as the world half-span visible along the
+/// canvas' shorter side, so it doesn't depend on the window size.
+struct Camera {
+    center: Vec2,
+    half_extent: f32,
+    mode: CameraMode,
+    /// Framing the fit-all mode is easing toward.
+    goal: Option<(Vec2, f32)>,
+}
+
+impl Camera {
+    const MIN_HALF_EXTENT: f32 = 2.0;
+    const MAX_HALF_EXTENT: f32 = 100_000.0;
+
+    fn new(half_extent: f32, mode: CameraMode) -> Self {
+        Self {
+            center: Vec2::ZERO,
+            half_extent: half_extent.clamp(Self::MIN_HALF_EXTENT, Self::MAX_HALF_EXTENT),
+            mode,
+            goal: None,
+        }
+    }
+
+    fn set_mode(&mut self, mode: CameraMode) {
+        self.mode = mode;
+        self.goal = None;
+    }
+
+    /// Screen pixels per world unit.
+    fn scale(&self, rect: Rect) -> f32 {
+        0.5 * rect.width().min(rect.height()) / self.half_extent
+    }
+
+    fn to_screen(&self, rect: Rect, wx: f32, wy: f32) -> Pos2 {
+        let s = self.scale(rect);
+        rect.center() + Vec2::new(wx - self.center.x, self.center.y - wy) * s
+    }
+
+    fn to_world(&self, rect: Rect, p: Pos2) -> Vec2 {
+        let s = self.scale(rect);
+        let d = (p - rect.center()) / s;
+        Vec2::new(self.center.x + d.x, self.center.y - d.y)
+    }
+
+    /// Visible world rectangle as (min, max) corners.
+    fn visible(&self, rect: Rect) -> (Vec2, Vec2) {
+        let a = self.to_world(rect, rect.left_bottom());
+        let b = self.to_world(rect, rect.right_top());
+        (a, b)
+    }
+
+    /// Pan by a screen-space drag.
+    fn pan(&mut self, rect: Rect, delta: Vec2) {
+        let s = self.scale(rect);
+        self.center += Vec2::new(-delta.x, delta.y) / s;
+    }
+
+    /// Zoom by `factor` (> 1 zooms in) keeping the world point under `anchor` fixed.
+    fn zoom_at(&mut self, rect: Rect, anchor: Pos2, factor: f32) {
+        let before = self.to_world(rect, anchor);
+        self.half_extent =
+            (self.half_extent / factor).clamp(Self::MIN_HALF_EXTENT, Self::MAX_HALF_EXTENT);
+        let after = self.to_world(rect, anchor);
+        self.center += before - after;
+    }
+
+    /// Keep `bounds` in view. Hysteresis: only re-frame once content leaves the
+    /// view or shrinks well inside it, then ease toward the new framing.
+    fn auto_fit(&mut self, rect: Rect, bounds: Bounds) {
+        let short = rect.width().min(rect.height()).max(1.0);
+        let needed = 0.5
+            * (bounds.width() * short / rect.width().max(1.0))
+                .max(bounds.height() * short / rect.height().max(1.0));
+        let needed = needed.max(Self::MIN_HALF_EXTENT * 5.0);
+        let center = Vec2::new(
+            0.5 * (bounds.min[0] + bounds.max[0]),
+            0.5 * (bounds.min[1] + bounds.max[1]),
+        );
+
+        // Compare against where we're heading, not the mid-ease view
+        let (goal_center, goal_half) = self.goal.unwrap_or((self.center, self.half_extent));
+        let goal_span = Vec2::new(rect.width(), rect.height()) * (goal_half / short);
+        let goal_min = goal_center - goal_span;
+        let goal_max = goal_center + goal_span;
+        let inside = bounds.min[0] >= goal_min.x
+            && bounds.min[1] >= goal_min.y
+            && bounds.max[0] <= goal_max.x
+            && bounds.max[1] <= goal_max.y;
+        if !inside || needed < FIT_SHRINK_RATIO * goal_half {
+            // Leave headroom so steady drift doesn't re-frame every frame
+            self.goal = Some((center, (needed * FIT_MARGIN).min(Self::MAX_HALF_EXTENT)));
+        }
+
+        if let Some((c, h)) = self.goal {
+            self.center += (c - self.center) * FIT_EASE;
+            self.half_extent += (h - self.half_extent) * FIT_EASE;
+            if (c - self.center).length() < 0.01 * h && (h - self.half_extent).abs() < 0.01 * h {
+                self.center = c;
+                self.half_extent = h;
+            }
+        }
+    }
+
+    /// Jump to frame `bounds` (with the fit margin) and stop auto-moving.
+    fn frame(&mut self, bounds: Bounds) {
+        self.set_mode(CameraMode::Free);
+        self.center = Vec2::new(
+            0.5 * (bounds.min[0] + bounds.max[0]),
+            0.5 * (bounds.min[1] + bounds.max[1]),
+        );
+        let needed = (0.5 * bounds.width().max(bounds.height())).max(Self::MIN_HALF_EXTENT * 5.0);
+        self.half_extent = (needed * FIT_MARGIN).min(Self::MAX_HALF_EXTENT);
+    }
+
+    /// World grid step giving lines roughly `min_px` apart: 1, 2 or 5 times a power of ten.
+    fn grid_step(&self, rect: Rect, min_px: f32) -> f32 {
+        let raw = min_px / self.scale(rect);
+        let pow = 10f32.powf(raw.log10().floor());
+        [1.0, 2.0, 5.0, 10.0]
+            .into_iter()
+            .map(|m| m * pow)
+            .find(|&step| step >= raw)
+            .unwrap_or(10.0 * pow)
+    }
+}
+
+/* ------------------------------ Tile layer ------------------------------ */
+
+// Tile layer limits: tiles drawn per frame, tiles decoded per frame (the rest
+// arrive over the next frames), and cached textures before pruning
+const MAX_VISIBLE_TILES: usize = 96;
+const TILE_LOADS_PER_FRAME: usize = 6;
+const TILE_CACHE_MAX: usize = 512;
+
+/// Offline raster background, drawn in the ENU frame of the home point.
+struct TileLayer {
+    source: TileSource,
+    zooms: (u8, u8),
+    /// Loaded textures; `None` marks a tile that is missing or failed to decode.
+    cache: HashMap<TileId, Option<egui::TextureHandle>>,
+    enabled: bool,
+}
+
+impl TileLayer {
+    fn open(path: &Path) -> Result<Self, TileError> {
+        let source = TileSource::open(path)?;
+        let zooms = source.zoom_range()?.ok_or_else(|| TileError::Empty(path.into()))?;
+        Ok(Self {
+            source,
+            zooms,
+            cache: HashMap::new(),
+            enabled: true,
+        })
+    }
+
+    /// Tile range covering the visible world box at zoom `z`.
+    fn visible_tiles(&self, z: u8, home: &LocalFrame, min: Vec2, max: Vec2) -> Vec<TileId> {
+        let corners = [(min.x, min.y), (min.x, max.y), (max.x, min.y), (max.x, max.y)];
+        let (mut x0, mut y0, mut x1, mut y1) = (f64::MAX, f64::MAX, f64::MIN, f64::MIN);
+        for (e, n) in corners {
+            let g = home.from_enu([e as f64, n as f64, 0.0]);
+            let (tx, ty) = tile_xy(g.lat, g.lon, z);
+            x0 = x0.min(tx);
+            y0 = y0.min(ty);
+            x1 = x1.max(tx);
+            y1 = y1.max(ty);
+        }
+        let last = tiles_per_side(z) - 1.0;
+        let (x0, x1) = (x0.floor().clamp(0.0, last) as u32, x1.floor().clamp(0.0, last) as u32);
+        let (y0, y1) = (y0.floor().clamp(0.0, last) as u32, y1.floor().clamp(0.0, last) as u32);
+        (y0..=y1)
+            .flat_map(|y| (x0..=x1).map(move |x| TileId { z, x, y }))
+            .collect()
+    }
+
+    /// Draw the tiles under the current camera. Missing tiles are left empty so
+    /// the grid underneath shows through.
+    fn draw(
+        &mut self,
+        ctx: &egui::Context,
+        painter: &egui::Painter,
+        rect: Rect,
+        camera: &Camera,
+        home: &LocalFrame,
+    ) {
+        let m_per_px = 1.0 / camera.scale(rect) as f64;
+        let ideal = zoom_for(home.origin().lat, m_per_px).round();
+        let mut z = ideal.clamp(self.zooms.0 as f64, self.zooms.1 as f64) as u8;
+        let (vis_min, vis_max) = camera.visible(rect);
+        let mut tiles = self.visible_tiles(z, home, vis_min, vis_max);
+        while tiles.len() > MAX_VISIBLE_TILES && z > self.zooms.0 {
+            z -= 1;
+            tiles = self.visible_tiles(z, home, vis_min, vis_max);
+        }
+        if tiles.len() > MAX_VISIBLE_TILES {
+            return;
+        }
+
+        if self.cache.len() > TILE_CACHE_MAX {
+            self.cache.retain(|id, _| id.z == z);
+        }
+
+        let mut loads = 0;
+        for id in tiles {
+            if !self.cache.contains_key(&id) {
+                if loads == TILE_LOADS_PER_FRAME {
+                    ctx.request_repaint();
+                    continue;
+                }
+                loads += 1;
+                let texture = match self.source.load(id) {
+                    Ok(Some(img)) => Some(ctx.load_texture(
+                        format!("tile/{}/{}/{}", id.z, id.x, id.y),
+                        egui::ColorImage::from_rgba_unmultiplied([img.width, img.height], &img.rgba),
+                        egui::TextureOptions::LINEAR,
+                    )),
+                    Ok(None) => None,
+                    Err(e) => {
+                        eprintln!("dashboard: tile {}/{}/{}: {e}", id.z, id.x, id.y);
+                        None
+                    }
+                };
+                self.cache.insert(id, texture);
+            }
+            let Some(Some(texture)) = self.cache.get(&id) else {
+                continue;
+            };
+
+            // Corners NW, NE, SE, SW; a quad rather than a rect since ENU is not Mercator
+            let mut mesh = egui::Mesh::with_texture(texture.id());
+            let tint = Color32::from_gray(170);
+            for (dx, dy) in [(0, 0), (1, 0), (1, 1), (0, 1)] {
+                let (lat, lon) = TileId::corner(z, id.x + dx, id.y + dy);
+                let [e, n, _] = home.to_enu(&Geodetic::new(lat, lon, home.origin().alt));
+                let pos = camera.to_screen(rect, e as f32, n as f32);
+                mesh.vertices.push(egui::epaint::Vertex {
+                    pos,
+                    uv: Pos2::new(dx as f32, dy as f32),
+                    color: tint,
+                });
+            }
+            mesh.add_triangle(0, 1, 2);
+            mesh.add_triangle(0, 2, 3);
+            painter.add(Shape::mesh(mesh));
+        }
+    }
+}
+
+/* ------------------------------- Geofences ------------------------------- */
+
+/// A geofence being set up in the Geofences window or drawn on the map.
+#[derive(Clone)]
+struct FenceDraft {
+    name: String,
+    kind: FenceKind,
+    circle: bool,
+    floor: Option<f32>,
+    ceiling: Option<f32>,
+    /// Polygon vertices, or center then rim point for a circle (world units).
+    points: Vec<Vec2>,
+}
+
+impl Default for FenceDraft {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            kind: FenceKind::Exclusion,
+            circle: false,
+            floor: None,
+            ceiling: None,
+            points: Vec::new(),
+        }
+    }
+}
+
+impl FenceDraft {
+    /// The shape `pts` would make, once there are enough of them.
+    fn preview(&self, pts: &[Vec2]) -> Option<FenceShape> {
+        if self.circle {
+            let [center, rim, ..] = pts else {
+                return None;
+            };
+            Some(FenceShape::Circle {
+                center: [center.x, center.y],
+                radius: (*rim - *center).length(),
+            })
+        } else {
+            (pts.len() >= 3).then(|| FenceShape::Polygon(pts.iter().map(|p| [p.x, p.y]).collect()))
+        }
+    }
+
+    fn build(&self) -> Option<Geofence> {
+        let shape = self.preview(&self.points)?;
+        if matches!(shape, FenceShape::Circle { radius, .. } if radius <= 0.0) {
+            return None;
+        }
+        Some(Geofence {
+            name: self.name.clone(),
+            kind: self.kind,
+            shape,
+            floor: self.floor,
+            ceiling: self.ceiling,
+        })
+    }
+}
+
 /* ----------------------------- UI helpers ----------------------------- */
 
+// Off-screen drone indicators sit this far inside the canvas edge
+const EDGE_ARROW_INSET: f32 = 18.0;
+
 fn glass_card(ui: &mut egui::Ui, size: Vec2, body: impl FnOnce(&mut egui::Ui, Rect)) {
     egui::Frame::none()
         .fill(Color32::from_rgba_unmultiplied(255, 255, 255, 10))
@@ -273,6 +1454,165 @@ fn status_badge(ui: &mut egui::Ui, status: &str) {
         });
 }
 
+/// Distance label: metres (km past 1000) in a geodetic frame, plain units otherwise.
+fn format_distance(v: f32, metric: bool) -> String {
+    let (v, unit) = match metric {
+        true if v >= 1000.0 => (v / 1000.0, "km"),
+        true => (v, "m"),
+        false => (v, "u"),
+    };
+    if v >= 10.0 || v.fract() == 0.0 {
+        format!("{v:.0} {unit}")
+    } else {
+        format!("{v:.1} {unit}")
+    }
+}
+
+/// Speed unit matching [`format_distance`].
+fn speed_unit(metric: bool) -> &'static str {
+    if metric {
+        "m/s"
+    } else {
+        "u/s"
+    }
+}
+
+/// Geofence tint: blue for inclusion zones, red for exclusion zones.
+fn fence_color(kind: FenceKind, alpha: u8) -> Color32 {
+    match kind {
+        FenceKind::Inclusion => Color32::from_rgba_unmultiplied(90, 190, 255, alpha),
+        FenceKind::Exclusion => Color32::from_rgba_unmultiplied(255, 90, 90, alpha),
+    }
+}
+
+/// Translucent fill plus outline for one fence shape.
+fn draw_fence(
+    painter: &egui::Painter,
+    shape: &FenceShape,
+    kind: FenceKind,
+    highlight: bool,
+    to_screen: &impl Fn(f32, f32) -> Pos2,
+    scale: f32,
+) {
+    let fill = fence_color(kind, if highlight { 48 } else { 24 });
+    let stroke = Stroke::new(
+        if highlight { 2.5 } else { 1.5 },
+        fence_color(kind, if highlight { 230 } else { 150 }),
+    );
+    match shape {
+        FenceShape::Circle { center, radius } => {
+            painter.circle(to_screen(center[0], center[1]), radius * scale, fill, stroke);
+        }
+        FenceShape::Polygon(pts) => {
+            let screen: Vec<Pos2> = pts.iter().map(|p| to_screen(p[0], p[1])).collect();
+            // Fill through a triangulation: egui only fills convex polygons itself
+            let mut mesh = egui::Mesh::default();
+            for &pos in &screen {
+                mesh.colored_vertex(pos, fill);
+            }
+            for [a, b, c] in shape.triangles() {
+                mesh.add_triangle(a as u32, b as u32, c as u32);
+            }
+            painter.add(Shape::mesh(mesh));
+            painter.add(Shape::closed_line(screen, stroke));
+        }
+    }
+}
+
+/// Arrow on the border of `inner` pointing from its center toward an off-screen `target`.
+fn edge_arrow(painter: &egui::Painter, inner: Rect, target: Pos2, color: Color32, label: &str) {
+    let c = inner.center();
+    let d = target - c;
+    if d.length() < 1e-3 {
+        return;
+    }
+    // Scale the center->target ray down until it touches the border
+    let tx = 0.5 * inner.width() / d.x.abs().max(1e-3);
+    let ty = 0.5 * inner.height() / d.y.abs().max(1e-3);
+    let tip = c + d * tx.min(ty).min(1.0);
+    let dir = d.normalized();
+    let perp = Vec2::new(-dir.y, dir.x);
+    let base = tip - dir * 14.0;
+    painter.add(Shape::convex_polygon(
+        vec![tip, base + perp * 7.0, base - perp * 7.0],
+        color,
+        Stroke::new(1.0, Color32::from_rgba_unmultiplied(255, 255, 255, 60)),
+    ));
+    painter.text(
+        tip - dir * 34.0,
+        egui::Align2::CENTER_CENTER,
+        label,
+        FontId::proportional(12.0),
+        Color32::from_rgb(210, 218, 230),
+    );
+}
+
+fn lifecycle_color(lifecycle: Lifecycle) -> Color32 {
+    match lifecycle {
+        Lifecycle::Active => Color32::from_rgb(120, 220, 160),
+        Lifecycle::Stale => Color32::from_rgb(255, 200, 120),
+        Lifecycle::Lost => Color32::from_rgb(255, 110, 110),
+        Lifecycle::Landed => Color32::from_rgb(140, 190, 255),
+    }
+}
+
+/// Stable color derived from a drone ID.
+fn drone_rgb(id: u32) -> (u8, u8, u8) {
+    let mut h = id;
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x7feb_352d);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x846c_a68b);
+    h ^= h >> 16;
+    ((h & 0xFF) as u8, ((h >> 8) & 0xFF) as u8, ((h >> 16) & 0xFF) as u8)
+}
+
+/// Green / amber / red for a 0..1 link quality score.
+fn link_quality_color(quality: f32) -> Color32 {
+    if quality >= 0.9 {
+        Color32::from_rgb(120, 220, 160)
+    } else if quality >= 0.6 {
+        Color32::from_rgb(255, 200, 120)
+    } else {
+        Color32::from_rgb(255, 110, 110)
+    }
+}
+
+fn severity_color(severity: Severity) -> Color32 {
+    match severity {
+        Severity::Info => Color32::from_rgb(140, 190, 255),
+        Severity::Warning => Color32::from_rgb(255, 200, 120),
+        Severity::Critical => Color32::from_rgb(255, 110, 110),
+    }
+}
+
+/// Time of day (UTC) of a wall-clock timestamp, `HH:MM:SS`.
+fn format_clock(ms: u128) -> String {
+    let s = ms / 1000;
+    format!("{:02}:{:02}:{:02}", s / 3600 % 24, s / 60 % 60, s % 60)
+}
+
+/// Outline of an axis-aligned ellipse around `center`, in screen space.
+fn ellipse_points(center: Pos2, radii: Vec2) -> Vec<Pos2> {
+    const SEGMENTS: usize = 40;
+    (0..SEGMENTS)
+        .map(|i| {
+            let a = i as f32 / SEGMENTS as f32 * std::f32::consts::TAU;
+            center + Vec2::new(radii.x * a.cos(), radii.y * a.sin())
+        })
+        .collect()
+}
+
+/// Compact age such as "42 s" or "3 min".
+fn format_age(ms: u128) -> String {
+    let s = ms / 1000;
+    match s {
+        0..=99 => format!("{s} s"),
+        100..=5999 => format!("{} min", s / 60),
+        _ => format!("{} h", s / 3600),
+    }
+}
+
 fn numeric_tile_wh(ui: &mut egui::Ui, title: &str, value: &str, w: f32, h: f32) {
     glass_card(ui, egui::vec2(w, h), |ui, rect| {
         let painter = ui.painter_at(rect);
@@ -331,26 +1671,84 @@ impl eframe::App for App {
             self.styled_once = true;
         }
 
+        // Replay drives the shared state from the UI thread
+        if let Some(replay) = self.replay.as_mut() {
+            replay.tick(&mut self.state.lock().unwrap());
+        }
+
+        // Keep the fleet multi-selection in step with selections made elsewhere
+        match self.selected {
+            Some(id) if !self.fleet_selection.contains(&id) => {
+                self.fleet_selection = BTreeSet::from([id]);
+                self.fleet_anchor = Some(id);
+            }
+            None => self.fleet_selection.clear(),
+            _ => {}
+        }
+
+        // Lifecycles and rules run every frame so silence alone moves drones to
+        // stale / lost and fires time-based rules
+        let wall_ms = self.replay.as_ref().map_or_else(now_ms, |r| r.cursor_ms());
+        let raised = {
+            let mut state = self.state.lock().unwrap();
+            state.update_lifecycles();
+            state.check_alerts(wall_ms)
+        };
+        if raised.flash {
+            self.flash = raised.worst.map(|s| (Instant::now(), s));
+        }
+        if raised.sound {
+            // Terminal bell: the only sound available without an audio stack
+            eprint!("\x07");
+        }
+
         /* ------------------------ top bar: chips ------------------------ */
         egui::TopBottomPanel::top("top").show(ctx, |ui| {
-            let (drones, total, age_ms) = {
+            let (lifecycles, total, age_ms, breaching, unacked, worst) = {
                 let guard = self.state.lock().unwrap();
+                let worst = guard
+                    .alerts
+                    .active()
+                    .filter(|a| !a.acked())
+                    .map(|a| a.severity)
+                    .max();
                 (
-                    guard.drones.len(),
+                    guard.lifecycle_counts(),
                     guard.total_packets,
                     guard
                         .last_packet_at
-                        .map(|t| t.elapsed().as_millis())
+                        .map(|t| guard.now().saturating_duration_since(t).as_millis())
                         .unwrap_or(0),
+                    guard.drones.values().filter(|d| !d.breaches.is_empty()).count(),
+                    guard.alerts.unacked(),
+                    worst,
                 )
             };
+            let rejected = self.state.lock().unwrap().diagnostics.rejected;
+            // Per-receiver counts, when packets come in through more than one
+            let sources_hint = {
+                let guard = self.state.lock().unwrap();
+                (guard.sources.len() > 1).then(|| {
+                    guard
+                        .sources
+                        .iter()
+                        .map(|s| {
+                            format!(
+                                "{} (weight {}): {} packets, {} duplicates, {} late",
+                                s.name, s.weight, s.packets, s.duplicates, s.late
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                })
+            };
 
             ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
                 ui.heading("Telemetry Fusion Dashboard");
                 ui.add_space(12.0);
 
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                    let mut chip_fixed = |ui: &mut egui::Ui, text: String, min_w: f32| {
+                    let chip_fixed = |ui: &mut egui::Ui, text: String, min_w: f32| {
                         egui::Frame::none()
                             .fill(Color32::from_rgba_unmultiplied(255, 255, 255, 10))
                             .stroke(Stroke::new(
@@ -362,7 +1760,8 @@ impl eframe::App for App {
                             .show(ui, |ui| {
                                 ui.set_min_width(min_w);
                                 ui.label(RichText::new(text).monospace());
-                            });
+                            })
+                            .response
                     };
 
                     let last_secs = (age_ms as f32) / 1000.0;
@@ -373,8 +1772,34 @@ impl eframe::App for App {
                     };
 
                     chip_fixed(ui, last_text, 160.0);
-                    chip_fixed(ui, format!("Packets: {total}"), 140.0);
-                    chip_fixed(ui, format!("Drones: {drones}"), 120.0);
+                    let packets = chip_fixed(ui, format!("Packets: {total}"), 140.0);
+                    if let Some(hint) = sources_hint {
+                        packets.on_hover_text(hint);
+                    }
+
+                    // Drones by lifecycle state; states with nobody in them are skipped
+                    egui::Frame::none()
+                        .fill(Color32::from_rgba_unmultiplied(255, 255, 255, 10))
+                        .stroke(Stroke::new(
+                            1.0,
+                            Color32::from_rgba_unmultiplied(255, 255, 255, 24),
+                        ))
+                        .rounding(10.0)
+                        .inner_margin(Margin::symmetric(12.0, 8.0))
+                        .show(ui, |ui| {
+                            ui.spacing_mut().item_spacing.x = 8.0;
+                            // Right-to-left layout: push in reverse to read left to right
+                            for (l, n) in lifecycles.iter().rev() {
+                                if *n > 0 || *l == Lifecycle::Active {
+                                    ui.label(
+                                        RichText::new(format!("{n} {}", l.label()))
+                                            .monospace()
+                                            .color(lifecycle_color(*l)),
+                                    );
+                                }
+                            }
+                            ui.label(RichText::new("Drones:").monospace());
+                        });
 
                     egui::Frame::none()
                         .fill(Color32::from_rgba_unmultiplied(255, 255, 255, 10))
@@ -386,16 +1811,283 @@ impl eframe::App for App {
                         .inner_margin(Margin::symmetric(12.0, 6.0))
                         .show(ui, |ui| {
                             ui.toggle_value(&mut self.show_trails, "Trails");
+                            ui.toggle_value(&mut self.show_uncertainty, "σ")
+                                .on_hover_text("Kalman 2σ position uncertainty");
+                            ui.toggle_value(&mut self.show_predictions, "Predict")
+                                .on_hover_text("Dead-reckoned position of silent drones");
+                        });
+
+                    egui::Frame::none()
+                        .fill(Color32::from_rgba_unmultiplied(255, 255, 255, 10))
+                        .stroke(Stroke::new(
+                            1.0,
+                            Color32::from_rgba_unmultiplied(255, 255, 255, 24),
+                        ))
+                        .rounding(10.0)
+                        .inner_margin(Margin::symmetric(12.0, 6.0))
+                        .show(ui, |ui| {
+                            ui.toggle_value(&mut self.show_fleet, "Fleet");
+                        });
+
+                    egui::Frame::none()
+                        .fill(Color32::from_rgba_unmultiplied(255, 255, 255, 10))
+                        .stroke(Stroke::new(
+                            1.0,
+                            Color32::from_rgba_unmultiplied(255, 255, 255, 24),
+                        ))
+                        .rounding(10.0)
+                        .inner_margin(Margin::symmetric(12.0, 6.0))
+                        .show(ui, |ui| {
+                            let label = if breaching > 0 {
+                                RichText::new(format!("⚠ Fences {breaching}"))
+                                    .color(Color32::from_rgb(255, 110, 110))
+                            } else {
+                                RichText::new("Fences")
+                            };
+                            ui.toggle_value(&mut self.show_fences, label);
+                        });
+
+                    // Datagrams that failed to decode or validate
+                    egui::Frame::none()
+                        .fill(Color32::from_rgba_unmultiplied(255, 255, 255, 10))
+                        .stroke(Stroke::new(
+                            1.0,
+                            Color32::from_rgba_unmultiplied(255, 255, 255, 24),
+                        ))
+                        .rounding(10.0)
+                        .inner_margin(Margin::symmetric(12.0, 6.0))
+                        .show(ui, |ui| {
+                            let label = if rejected > 0 {
+                                RichText::new(format!("Rejected {rejected}"))
+                                    .color(Color32::from_rgb(255, 190, 90))
+                            } else {
+                                RichText::new("Diagnostics")
+                            };
+                            ui.toggle_value(&mut self.show_diagnostics, label);
+                        });
+
+                    // Alert counter: unacknowledged active alerts, blinking while flashing
+                    let flashing = self.flash.is_some_and(|(at, _)| at.elapsed() < ALERT_FLASH);
+                    let blink = flashing && (ui.input(|i| i.time) * 4.0) as i64 % 2 == 0;
+                    egui::Frame::none()
+                        .fill(match worst {
+                            Some(s) if blink => severity_color(s).gamma_multiply(0.5),
+                            _ => Color32::from_rgba_unmultiplied(255, 255, 255, 10),
+                        })
+                        .stroke(Stroke::new(
+                            1.0,
+                            Color32::from_rgba_unmultiplied(255, 255, 255, 24),
+                        ))
+                        .rounding(10.0)
+                        .inner_margin(Margin::symmetric(12.0, 6.0))
+                        .show(ui, |ui| {
+                            let label = match worst {
+                                Some(s) => RichText::new(format!("🔔 Alerts {unacked}"))
+                                    .color(severity_color(s)),
+                                None => RichText::new("🔔 Alerts"),
+                            };
+                            ui.toggle_value(&mut self.show_alerts, label);
+                        });
+
+                    if let Some(tiles) = self.tiles.as_mut() {
+                        egui::Frame::none()
+                            .fill(Color32::from_rgba_unmultiplied(255, 255, 255, 10))
+                            .stroke(Stroke::new(
+                                1.0,
+                                Color32::from_rgba_unmultiplied(255, 255, 255, 24),
+                            ))
+                            .rounding(10.0)
+                            .inner_margin(Margin::symmetric(12.0, 6.0))
+                            .show(ui, |ui| {
+                                let resp = ui.toggle_value(&mut tiles.enabled, "Tiles");
+                                if self.state.lock().unwrap().home.is_none() {
+                                    resp.on_hover_text(
+                                        "Tiles appear once a home point is known \
+                                         (--home or the first lat/lon packet)",
+                                    );
+                                }
+                            });
+                    }
+
+                    // Recording toggle (+ record count / error while active); live only
+                    if self.replay.is_none() {
+                        let mut rec = self.recording.lock().unwrap();
+                        let mut recording = rec.writer.is_some();
+                        if let Some(w) = rec.writer.as_ref() {
+                            chip_fixed(ui, format!("Rec: {}", w.records()), 110.0);
+                        } else if let Some(err) = rec.error.as_ref() {
+                            ui.label(
+                                RichText::new(format!("Rec failed: {err}"))
+                                    .small()
+                                    .color(Color32::from_rgb(255, 160, 160)),
+                            );
+                        }
+                        egui::Frame::none()
+                            .fill(Color32::from_rgba_unmultiplied(255, 255, 255, 10))
+                            .stroke(Stroke::new(
+                                1.0,
+                                Color32::from_rgba_unmultiplied(255, 255, 255, 24),
+                            ))
+                            .rounding(10.0)
+                            .inner_margin(Margin::symmetric(12.0, 6.0))
+                            .show(ui, |ui| {
+                                let label = if recording {
+                                    RichText::new("● Rec").color(Color32::from_rgb(255, 110, 110))
+                                } else {
+                                    RichText::new("● Rec")
+                                };
+                                let resp = ui.toggle_value(&mut recording, label);
+                                if let Some(path) = rec.writer.as_ref().map(|w| w.path().display()) {
+                                    resp.on_hover_text(format!("Recording to {path}"));
+                                }
+                            });
+                        if recording != rec.writer.is_some() {
+                            if recording {
+                                rec.start();
+                            } else {
+                                rec.stop();
+                            }
+                        }
+                    } else {
+                        chip_fixed(ui, "REPLAY".to_string(), 80.0);
+                    }
+                });
+            });
+        });
+
+        /* --------------------- bottom bar: replay controls -------------------- */
+        if let Some(replay) = self.replay.as_mut() {
+            egui::TopBottomPanel::bottom("replay").show(ctx, |ui| {
+                ui.add_space(4.0);
+                ui.horizontal(|ui| {
+                    let mut state = self.state.lock().unwrap();
+
+                    if ui.button("⏮").on_hover_text("Previous frame").clicked() {
+                        replay.set_playing(false);
+                        replay.step_back(&mut state);
+                    }
+                    let play_label = if replay.playing { "⏸ Pause" } else { "▶ Play" };
+                    if ui.button(play_label).clicked() {
+                        if !replay.playing && replay.at_end() {
+                            replay.seek(replay.start_ms(), &mut state);
+                        }
+                        let playing = !replay.playing;
+                        replay.set_playing(playing);
+                    }
+                    if ui.button("⏭").on_hover_text("Next frame").clicked() {
+                        replay.set_playing(false);
+                        replay.step_forward(&mut state);
+                    }
+
+                    egui::ComboBox::from_id_source("replay_speed")
+                        .width(70.0)
+                        .selected_text(format!("{}x", replay.speed))
+                        .show_ui(ui, |ui| {
+                            for s in SPEEDS {
+                                ui.selectable_value(&mut replay.speed, s, format!("{s}x"));
+                            }
                         });
+
+                    let fmt = |d: Duration| {
+                        let secs = d.as_secs_f32();
+                        format!("{:02}:{:05.2}", (secs / 60.0) as u32, secs % 60.0)
+                    };
+                    ui.label(
+                        RichText::new(format!(
+                            "{} / {}",
+                            fmt(replay.position()),
+                            fmt(replay.duration())
+                        ))
+                        .monospace(),
+                    );
+
+                    // Seek bar over the recording's ts_ms range
+                    let span = (replay.end_ms() - replay.start_ms()) as f64;
+                    let mut pos = (replay.cursor_ms() - replay.start_ms()) as f64;
+                    ui.spacing_mut().slider_width = ui.available_width() - 24.0;
+                    let resp = ui.add(
+                        egui::Slider::new(&mut pos, 0.0..=span.max(1.0))
+                            .show_value(false)
+                            .trailing_fill(true),
+                    );
+                    if resp.changed() {
+                        replay.seek(replay.start_ms() + pos as u128, &mut state);
+                    }
                 });
+                ui.add_space(2.0);
             });
-        });
+        }
+
+        /* ------------------------- fleet table: left panel ------------------------- */
+        if self.show_fleet {
+            egui::SidePanel::left("fleet")
+                .resizable(true)
+                .default_width(520.0)
+                .show(ctx, |ui| self.fleet_ui(ui));
+        }
+
+        /* ----------------------- alerts: docked side panel ----------------------- */
+        if self.show_alerts && self.alerts_docked {
+            egui::SidePanel::right("alerts")
+                .resizable(true)
+                .default_width(600.0)
+                .show(ctx, |ui| self.alerts_ui(ui));
+        }
 
         /* ------------------------ center panel: map ----------------------- */
         egui::CentralPanel::default().show(ctx, |ui| {
             let available = ui.available_size();
             let rect = ui.allocate_space(available).1;
             let painter = ui.painter_at(rect);
+            let resp = ui.interact(rect, Id::new("canvas"), Sense::click_and_drag());
+
+            // Snapshot the state so we don't hold the mutex while painting
+            let (now, bounds, home, fences, tracking, snapshot) = {
+                let guard = self.state.lock().unwrap();
+                let drones: Vec<(u32, DroneState)> =
+                    guard.drones.iter().map(|(k, v)| (*k, v.clone())).collect();
+                let fences = guard.fences.clone();
+                (guard.now(), guard.bounds(), guard.home, fences, guard.tracking, drones)
+            };
+            // With a home point the world frame is in metres
+            let metric = home.is_some();
+
+            // ---- Camera: user input first, then the automatic modes ----
+            if resp.dragged() {
+                self.camera.pan(rect, resp.drag_delta());
+                self.camera.set_mode(CameraMode::Free);
+            }
+            if let Some(hover) = resp.hover_pos() {
+                let (scroll, pinch) = ui.input(|i| (i.smooth_scroll_delta.y, i.zoom_delta()));
+                let factor = (scroll * 0.002).exp() * pinch;
+                if factor != 1.0 {
+                    // Following keeps the drone centered, so zoom around it instead
+                    let anchor = if self.camera.mode == CameraMode::Follow {
+                        rect.center()
+                    } else {
+                        hover
+                    };
+                    self.camera.zoom_at(rect, anchor, factor);
+                    if self.camera.mode == CameraMode::FitAll {
+                        self.camera.set_mode(CameraMode::Free);
+                    }
+                }
+            }
+            match self.camera.mode {
+                CameraMode::Free => {}
+                CameraMode::FitAll => {
+                    if let Some(bounds) = bounds {
+                        self.camera.auto_fit(rect, bounds);
+                    }
+                }
+                CameraMode::Follow => {
+                    if let Some((_, d)) = snapshot.iter().find(|(id, _)| Some(*id) == self.selected)
+                    {
+                        self.camera.center = Vec2::new(d.smoothed_x, d.smoothed_y);
+                    }
+                }
+            }
+            let camera = &self.camera;
 
             // deep base + soft inner border/vignette
             painter.rect_filled(rect, 14.0, Color32::from_rgb(10, 11, 14));
@@ -410,66 +2102,145 @@ impl eframe::App for App {
                 Color32::from_rgba_unmultiplied(255, 255, 255, 4),
             );
 
-            // soft grid
-            let grid_spacing = 56.0;
+            // soft grid on round world coordinates; the axes a little brighter
+            let grid_step = camera.grid_step(rect, 56.0);
             let grid_col = Color32::from_rgba_unmultiplied(140, 150, 170, 26);
-            let mut gx = rect.left().ceil();
-            while gx <= rect.right() {
+            let axis_col = Color32::from_rgba_unmultiplied(140, 150, 170, 60);
+            let (vis_min, vis_max) = camera.visible(rect);
+            let mut wx = (vis_min.x / grid_step).ceil() * grid_step;
+            while wx <= vis_max.x {
+                let sx = camera.to_screen(rect, wx, 0.0).x;
+                let col = if wx.abs() < grid_step * 0.5 { axis_col } else { grid_col };
                 painter.line_segment(
-                    [Pos2::new(gx, rect.top()), Pos2::new(gx, rect.bottom())],
-                    (1.0, grid_col),
+                    [Pos2::new(sx, rect.top()), Pos2::new(sx, rect.bottom())],
+                    (1.0, col),
                 );
-                gx += grid_spacing;
+                wx += grid_step;
             }
-            let mut gy = rect.top().ceil();
-            while gy <= rect.bottom() {
+            let mut wy = (vis_min.y / grid_step).ceil() * grid_step;
+            while wy <= vis_max.y {
+                let sy = camera.to_screen(rect, 0.0, wy).y;
+                let col = if wy.abs() < grid_step * 0.5 { axis_col } else { grid_col };
                 painter.line_segment(
-                    [Pos2::new(rect.left(), gy), Pos2::new(rect.right(), gy)],
-                    (1.0, grid_col),
+                    [Pos2::new(rect.left(), sy), Pos2::new(rect.right(), sy)],
+                    (1.0, col),
                 );
-                gy += grid_spacing;
+                wy += grid_step;
+            }
+
+            // Offline map tiles over the grid (needs a home point to place them)
+            if let (Some(tiles), Some(home)) = (self.tiles.as_mut(), home.as_ref()) {
+                if tiles.enabled {
+                    tiles.draw(ctx, &painter, rect, camera, home);
+                }
             }
 
             // World -> screen transform
-            let world = self.world_extent;
-            let to_screen = |wx: f32, wy: f32| -> Pos2 {
-                let nx = (wx + world) / (2.0 * world);
-                let ny = (wy + world) / (2.0 * world);
-                Pos2::new(
-                    rect.left() + nx * rect.width(),
-                    rect.bottom() - ny * rect.height(),
-                )
-            };
+            let to_screen = |wx: f32, wy: f32| -> Pos2 { camera.to_screen(rect, wx, wy) };
+
+            // Geofences as translucent overlays; a fence someone is breaching gets a bolder edge
+            for f in &fences {
+                let breached = snapshot
+                    .iter()
+                    .any(|(_, d)| d.breaches.iter().any(|b| b.fence == f.name));
+                draw_fence(&painter, &f.shape, f.kind, breached, &to_screen, camera.scale(rect));
+                let [lx, ly] = f.shape.label_point();
+                painter.text(
+                    to_screen(lx, ly),
+                    egui::Align2::CENTER_CENTER,
+                    &f.name,
+                    FontId::proportional(12.0),
+                    Color32::from_rgba_unmultiplied(230, 235, 245, 150),
+                );
+            }
 
-            // Snapshot the state so we don't hold the mutex while painting
-            let snapshot: Vec<(u32, DroneState)> = {
-                let guard = self.state.lock().unwrap();
-                guard.drones.iter().map(|(k, v)| (*k, v.clone())).collect()
-            };
+            // Fence being drawn: placed vertices plus a rubber band to the cursor
+            if let Some(draft) = &self.fence_draft {
+                let mut pts = draft.points.clone();
+                if let Some(hover) = resp.hover_pos() {
+                    pts.push(camera.to_world(rect, hover));
+                }
+                if let Some(shape) = draft.preview(&pts) {
+                    draw_fence(&painter, &shape, draft.kind, false, &to_screen, camera.scale(rect));
+                } else if pts.len() == 2 {
+                    painter.line_segment(
+                        [to_screen(pts[0].x, pts[0].y), to_screen(pts[1].x, pts[1].y)],
+                        Stroke::new(1.5, fence_color(draft.kind, 200)),
+                    );
+                }
+                for p in &draft.points {
+                    painter.circle_filled(to_screen(p.x, p.y), 4.0, fence_color(draft.kind, 230));
+                }
+            }
 
             let mut screen_positions: Vec<(u32, Pos2, Color32)> = Vec::with_capacity(snapshot.len());
 
             for (id, d) in snapshot.iter() {
-                // Stable color derived from ID
-                let mut h = *id as u32;
-                h ^= h >> 16;
-                h = h.wrapping_mul(0x7feb_352d);
-                h ^= h >> 15;
-                h = h.wrapping_mul(0x846c_a68b);
-                h ^= h >> 16;
-                let r = (h & 0xFF) as u8;
-                let g = ((h >> 8) & 0xFF) as u8;
-                let b = ((h >> 16) & 0xFF) as u8;
+                let (r, g, b) = drone_rgb(*id);
 
                 let p = to_screen(d.smoothed_x, d.smoothed_y);
 
-                // Fade whole drone if no packet for >2s
-                let age = d.last_seen.elapsed();
-                let dot_alpha = if age > Duration::from_secs(2) { 80 } else { 220 };
+                // Fade stale and landed drones
+                let dot_alpha = match d.lifecycle {
+                    Lifecycle::Active => 220,
+                    Lifecycle::Landed => 140,
+                    Lifecycle::Stale | Lifecycle::Lost => 80,
+                };
                 let dot_color = Color32::from_rgba_unmultiplied(r, g, b, dot_alpha);
 
                 screen_positions.push((*id, p, dot_color));
 
+                // Dead reckoning: dashed path from the last estimate to where the drone
+                // probably is now, ringed by the 2-sigma uncertainty
+                if let Some(pred) = d.predict(now, &tracking).filter(|_| self.show_predictions) {
+                    let q = to_screen(pred.pos[0], pred.pos[1]);
+                    let color = Color32::from_rgba_unmultiplied(r, g, b, 170);
+                    painter.extend(Shape::dashed_line(&[p, q], Stroke::new(1.4, color), 6.0, 4.0));
+                    let radius = (2.0 * pred.sigma * camera.scale(rect)).max(6.0);
+                    painter.circle(
+                        q,
+                        radius,
+                        Color32::from_rgba_unmultiplied(r, g, b, 18),
+                        Stroke::new(1.0, color),
+                    );
+                    painter.circle_filled(q, 3.5, color);
+                    painter.text(
+                        q + Vec2::new(8.0, 8.0),
+                        egui::Align2::LEFT_TOP,
+                        format!("+{:.1} s", pred.ahead.as_secs_f32()),
+                        FontId::proportional(11.0),
+                        Color32::from_rgba_unmultiplied(190, 200, 215, 170),
+                    );
+                }
+
+                // Lost: only a hollow ghost at the last known position
+                if d.lifecycle == Lifecycle::Lost {
+                    let ghost = Color32::from_rgba_unmultiplied(r, g, b, 110);
+                    painter.circle_stroke(p, 9.0, Stroke::new(1.5, ghost));
+                    let age = now.saturating_duration_since(d.last_seen).as_millis();
+                    painter.text(
+                        p + Vec2::new(14.0, 0.0),
+                        egui::Align2::LEFT_CENTER,
+                        format!("#{id} lost {}", format_age(age)),
+                        FontId::proportional(12.0),
+                        Color32::from_rgba_unmultiplied(190, 200, 215, 140),
+                    );
+                    continue;
+                }
+
+                // Off-screen: arrow on the canvas edge pointing toward the drone
+                let inner = rect.shrink(EDGE_ARROW_INSET);
+                if !inner.contains(p) {
+                    let dist = (Vec2::new(d.smoothed_x, d.smoothed_y) - camera.center).length();
+                    edge_arrow(
+                        &painter,
+                        inner,
+                        p,
+                        Color32::from_rgba_unmultiplied(r, g, b, dot_alpha.max(160)),
+                        &format!("#{id} {}", format_distance(dist, metric)),
+                    );
+                }
+
                 // ---- Trail ----
                 if self.show_trails && d.trail.len() >= 2 {
                     let mut pts: Vec<(Pos2, Instant)> = Vec::with_capacity(d.trail.len());
@@ -486,7 +2257,7 @@ impl eframe::App for App {
                         let (p1, _t1) = pts[w - 1];
                         let (p2, t2) = pts[w];
 
-                        let age = t2.elapsed();
+                        let age = now.saturating_duration_since(t2);
                         let alpha = if age <= FADE_START {
                             ALPHA_MAX
                         } else if age >= FADE_END {
@@ -510,8 +2281,33 @@ impl eframe::App for App {
                     }
                 }
 
+                // Kalman 2-sigma position ellipse
+                if let Some(k) = d.track.as_ref().filter(|_| self.show_uncertainty) {
+                    let [vx, vy, _] = k.position_variance();
+                    let s = camera.scale(rect);
+                    let radii = Vec2::new(2.0 * vx.sqrt() * s, 2.0 * vy.sqrt() * s);
+                    // Hidden under the dot while the track is tight
+                    if radii.max_elem() > 10.0 {
+                        painter.add(Shape::convex_polygon(
+                            ellipse_points(p, radii),
+                            Color32::from_rgba_unmultiplied(r, g, b, 28),
+                            Stroke::new(1.0, Color32::from_rgba_unmultiplied(r, g, b, 120)),
+                        ));
+                    }
+                }
+
+                // Geofence breach: pulsing red ring
+                if !d.breaches.is_empty() {
+                    let pulse = (ctx.input(|i| i.time) * 4.0).sin() as f32 * 0.5 + 0.5;
+                    painter.circle_stroke(
+                        p,
+                        16.0 + 4.0 * pulse,
+                        Stroke::new(2.0, Color32::from_rgba_unmultiplied(255, 80, 80, 200)),
+                    );
+                }
+
                 // Glow + dot + outline (highlight if selected)
-                let selected = self.selected == Some(*id);
+                let selected = self.fleet_selection.contains(id);
                 let halo_alpha = if selected { 100 } else { 60 };
                 let halo = Color32::from_rgba_unmultiplied(r, g, b, halo_alpha);
                 let dot_radius = if selected { 12.0 } else { 10.0 };
@@ -527,7 +2323,27 @@ impl eframe::App for App {
                     },
                 );
 
+                // Heading arrow just outside the dot (world +y is screen up)
+                if let Some(course) = d.course_deg() {
+                    let (s, c) = course.to_radians().sin_cos();
+                    let dir = Vec2::new(s, -c);
+                    let perp = Vec2::new(-dir.y, dir.x);
+                    let base = p + dir * (dot_radius + 2.0);
+                    painter.add(Shape::convex_polygon(
+                        vec![
+                            p + dir * (dot_radius + 11.0),
+                            base + perp * 5.0,
+                            base - perp * 5.0,
+                        ],
+                        Color32::from_rgba_unmultiplied(255, 255, 255, dot_alpha),
+                        Stroke::NONE,
+                    ));
+                }
+
                 // Label pill
+                if self.labels_selected_only && !selected {
+                    continue;
+                }
                 let label_text = format!("#{id}  {:.0}%  {}", d.battery, d.status);
                 let label_pos = p + Vec2::new(14.0, -16.0);
                 let label_bg = Color32::from_rgba_unmultiplied(0, 0, 0, 120);
@@ -543,30 +2359,134 @@ impl eframe::App for App {
                     FontId::proportional(14.0),
                     Color32::from_rgb(230, 235, 245),
                 );
+                // Link quality dot at the pill's right end
+                painter.circle_filled(
+                    Pos2::new(pill.right() - 12.0, pill.center().y),
+                    4.0,
+                    link_quality_color(d.link.quality()),
+                );
             }
 
-            // Click handling (hit-test near a drone)
-            let resp = ui.interact(rect, Id::new("canvas"), Sense::click());
-            if resp.clicked() {
-                if let Some(click_pos) = resp.interact_pointer_pos() {
-                    let mut best: Option<(u32, f32)> = None;
-                    let threshold_sq = 20.0 * 20.0;
-                    for (id, p, _color) in &screen_positions {
-                        let d2 = (p.x - click_pos.x).powi(2) + (p.y - click_pos.y).powi(2);
-                        if d2 <= threshold_sq {
-                            match best {
-                                None => best = Some((*id, d2)),
-                                Some((_bid, bd2)) if d2 < bd2 => best = Some((*id, d2)),
-                                _ => {}
-                            }
-                        }
+            // Scale bar, bottom-left
+            let bar_len = camera.grid_step(rect, 80.0);
+            let bar_px = bar_len * camera.scale(rect);
+            let bar_start = rect.left_bottom() + Vec2::new(18.0, -18.0);
+            let bar_end = bar_start + Vec2::new(bar_px, 0.0);
+            let bar_stroke = Stroke::new(2.0, Color32::from_rgb(210, 218, 230));
+            painter.line_segment([bar_start, bar_end], bar_stroke);
+            for x in [bar_start, bar_end] {
+                painter.line_segment([x, x - Vec2::new(0.0, 6.0)], bar_stroke);
+            }
+            painter.text(
+                bar_start + Vec2::new(bar_px * 0.5, -8.0),
+                egui::Align2::CENTER_BOTTOM,
+                format_distance(bar_len, metric),
+                FontId::proportional(12.0),
+                Color32::from_rgb(210, 218, 230),
+            );
+
+            // Cursor coordinate readout, bottom-right
+            if let Some(hover) = resp.hover_pos() {
+                let w = camera.to_world(rect, hover);
+                let mut text = format!("x {:>8.1}  y {:>8.1}", w.x, w.y);
+                if let Some(home) = home {
+                    let g = home.from_enu([w.x as f64, w.y as f64, 0.0]);
+                    text += &format!("   {:.6}°, {:.6}°", g.lat, g.lon);
+                }
+                let galley = painter.layout_no_wrap(
+                    text,
+                    FontId::monospace(12.0),
+                    Color32::from_rgb(210, 218, 230),
+                );
+                let pos = rect.right_bottom() - Vec2::new(18.0, 18.0) - galley.size();
+                painter.rect_filled(
+                    Rect::from_min_size(pos, galley.size()).expand(6.0),
+                    6.0,
+                    Color32::from_rgba_unmultiplied(0, 0, 0, 120),
+                );
+                painter.galley(pos, galley, Color32::WHITE);
+            }
+
+            // While drawing a fence, clicks place vertices instead of selecting drones.
+            // A circle is done after its rim click, a polygon on double click.
+            if let Some(draft) = self.fence_draft.as_mut() {
+                let mut done = None;
+                if resp.double_clicked() {
+                    // The first click of the pair already placed the last vertex
+                    done = draft.build();
+                } else if resp.clicked() {
+                    if let Some(click_pos) = resp.interact_pointer_pos() {
+                        draft.points.push(camera.to_world(rect, click_pos));
                     }
-                    self.selected = best.map(|(id, _)| id);
+                    if draft.circle && draft.points.len() == 2 {
+                        done = draft.build();
+                    }
+                }
+                let finished = done.is_some() || ui.input(|i| i.key_pressed(egui::Key::Escape));
+                if let Some(fence) = done {
+                    self.state.lock().unwrap().add_fence(fence);
+                }
+                if finished {
+                    self.fence_draft = None;
+                }
+            } else if resp.clicked() {
+                // Hit-test near a drone, in world units
+                if let Some(click_pos) = resp.interact_pointer_pos() {
+                    let click = camera.to_world(rect, click_pos);
+                    let threshold = 20.0 / camera.scale(rect);
+                    self.selected = snapshot
+                        .iter()
+                        .map(|(id, d)| {
+                            (*id, (Vec2::new(d.smoothed_x, d.smoothed_y) - click).length())
+                        })
+                        .filter(|&(_, dist)| dist <= threshold)
+                        .min_by(|a, b| a.1.total_cmp(&b.1))
+                        .map(|(id, _)| id);
                 } else {
                     self.selected = None;
                 }
+                if self.selected.is_none() && self.camera.mode == CameraMode::Follow {
+                    self.camera.set_mode(CameraMode::Free);
+                }
             }
 
+            // Camera mode buttons and zoom readout, top-left of the canvas
+            egui::Area::new(Id::new("camera_controls"))
+                .order(egui::Order::Foreground)
+                .fixed_pos(rect.left_top() + Vec2::new(12.0, 12.0))
+                .show(ctx, |ui| {
+                    egui::Frame::none()
+                        .fill(Color32::from_rgba_unmultiplied(24, 26, 31, 220))
+                        .stroke(Stroke::new(
+                            1.0,
+                            Color32::from_rgba_unmultiplied(255, 255, 255, 24),
+                        ))
+                        .rounding(10.0)
+                        .inner_margin(Margin::symmetric(8.0, 6.0))
+                        .show(ui, |ui| {
+                            ui.horizontal(|ui| {
+                                let mut mode = self.camera.mode;
+                                ui.selectable_value(&mut mode, CameraMode::Free, "Free");
+                                ui.selectable_value(&mut mode, CameraMode::FitAll, "Fit all");
+                                ui.add_enabled_ui(self.selected.is_some(), |ui| {
+                                    ui.selectable_value(&mut mode, CameraMode::Follow, "Follow")
+                                        .on_disabled_hover_text("Select a drone to follow");
+                                });
+                                if mode != self.camera.mode {
+                                    self.camera.set_mode(mode);
+                                }
+                                ui.label(
+                                    RichText::new(format!(
+                                        "±{}",
+                                        format_distance(self.camera.half_extent, metric)
+                                    ))
+                                    .monospace()
+                                    .small(),
+                                );
+                            });
+                        });
+                });
+
             // ===== Anchored HUD overlay next to the selected drone =====
             self.hud_open = self.selected.is_some();
 
@@ -579,7 +2499,12 @@ impl eframe::App for App {
 
                     // Card metrics
                     let card_w = 260.0;
-                    let card_h = 200.0;
+                    // One extra line per breach, and one for the Kalman track
+                    let extra_lines = snapshot
+                        .iter()
+                        .find(|(id, _)| *id == sel)
+                        .map_or(0, |(_, d)| d.breaches.len() + usize::from(d.track.is_some()));
+                    let card_h = 292.0 + 22.0 * extra_lines as f32;
 
                     // Prefer placing to the right/top of the drone, but clamp inside rect
                     let mut pos = *anchor + Vec2::new(18.0, -card_h - 12.0);
@@ -609,12 +2534,22 @@ impl eframe::App for App {
                                     ui.set_max_size(Vec2::new(card_w, card_h));
 
                                     // Snapshot drone
-                                    let snap = {
+                                    let (snap, source, feeds) = {
                                         let guard = self.state.lock().unwrap();
-                                        guard.drones.get(&sel).cloned()
+                                        let now = guard.now();
+                                        let d = guard.drones.get(&sel);
+                                        (
+                                            d.cloned().map(|d| (now, d)),
+                                            d.and_then(|d| guard.sources.get(d.source))
+                                                .map(|s| s.name.clone()),
+                                            d.map_or(0, |d| {
+                                                d.fresh_feeds(now, guard.lifecycle.stale_after)
+                                                    .count()
+                                            }),
+                                        )
                                     };
 
-                                    if let Some(d) = snap {
+                                    if let Some((now, d)) = snap {
                                         // Header
                                         ui.horizontal(|ui| {
                                             ui.monospace(format!("#{:04}", sel));
@@ -639,7 +2574,7 @@ impl eframe::App for App {
                                             glass_card(ui, Vec2::new(ring_w, ring_h), |ui, rect| {
                                                 let p = ui.painter_at(rect);
                                                 let v = (d.battery / 100.0).clamp(0.0, 1.0);
-                                                let col = if d.battery < 15.0 {
+                                                let col = if d.battery < LOW_BATTERY_PCT {
                                                     Color32::from_rgb(255, 110, 110)
                                                 } else {
                                                     Color32::from_rgb(120, 220, 160)
@@ -656,7 +2591,7 @@ impl eframe::App for App {
                                                     "Battery",
                                                 );
                                             });
-                                            let age = d.last_seen.elapsed();
+                                            let age = now.saturating_duration_since(d.last_seen);
                                             glass_card(ui, Vec2::new(ring_w, ring_h), |ui, rect| {
                                                 let p = ui.painter_at(rect);
                                                 let secs = age.as_secs_f32();
@@ -716,8 +2651,138 @@ impl eframe::App for App {
                                                         .monospace(),
                                                     );
                                                 });
+
+                                                // Optional kinematics, if the sender provides them
+                                                let hdg = d.course_deg();
+                                                let spd = d.speed();
+                                                if hdg.is_some() || spd.is_some() {
+                                                    ui.horizontal_wrapped(|ui| {
+                                                        let dash = || "  --".to_string();
+                                                        ui.label(
+                                                            RichText::new(format!(
+                                                                "hdg:{}°",
+                                                                hdg.map_or_else(dash, |h| format!(
+                                                                    "{h:>4.0}"
+                                                                ))
+                                                            ))
+                                                            .monospace(),
+                                                        );
+                                                        ui.separator();
+                                                        ui.label(
+                                                            RichText::new(format!(
+                                                                "spd:{} {}",
+                                                                spd.map_or_else(dash, |v| format!(
+                                                                    "{v:>4.1}"
+                                                                )),
+                                                                speed_unit(metric)
+                                                            ))
+                                                            .monospace(),
+                                                        );
+                                                    });
+                                                }
+
+                                                // Link health
+                                                let link = &d.link;
+                                                ui.horizontal_wrapped(|ui| {
+                                                    ui.label(
+                                                        RichText::new("●").color(
+                                                            link_quality_color(link.quality()),
+                                                        ),
+                                                    );
+                                                    ui.label(
+                                                        RichText::new(format!(
+                                                            "rx:{:>4.1} Hz",
+                                                            link.rate_hz(now)
+                                                        ))
+                                                        .monospace(),
+                                                    );
+                                                    ui.separator();
+                                                    let loss = if link.sequenced() {
+                                                        format!("{:>4.1}%", link.loss_ratio() * 100.0)
+                                                    } else {
+                                                        "  --".to_string()
+                                                    };
+                                                    ui.label(
+                                                        RichText::new(format!("loss:{loss}"))
+                                                            .monospace(),
+                                                    );
+                                                });
+                                                ui.horizontal_wrapped(|ui| {
+                                                    ui.label(
+                                                        RichText::new(format!(
+                                                            "ooo:{}/{}",
+                                                            link.out_of_order, d.late
+                                                        ))
+                                                        .monospace(),
+                                                    )
+                                                    .on_hover_text(
+                                                        "Out of sequence / older than the \
+                                                         latest applied packet (dropped)",
+                                                    );
+                                                    ui.separator();
+                                                    ui.label(
+                                                        RichText::new(format!(
+                                                            "jit:{:.0} ms",
+                                                            link.jitter_ms
+                                                        ))
+                                                        .monospace(),
+                                                    );
+                                                    ui.separator();
+                                                    ui.label(
+                                                        RichText::new(format!(
+                                                            "off:{:+.0} ms",
+                                                            link.clock_offset_ms
+                                                        ))
+                                                        .monospace(),
+                                                    );
+                                                });
+
+                                                // Receiver currently feeding the drone
+                                                if let Some(source) = &source {
+                                                    let others = if feeds > 1 {
+                                                        format!(" +{}", feeds - 1)
+                                                    } else {
+                                                        String::new()
+                                                    };
+                                                    let text = format!("src:{source}{others}");
+                                                    let hint = format!(
+                                                        "{feeds} receiver(s) heard it recently"
+                                                    );
+                                                    ui.label(RichText::new(text).monospace())
+                                                        .on_hover_text(hint);
+                                                }
+
+                                                // Kalman track: 1-sigma horizontal error,
+                                                // outliers gated out and restarts
+                                                if let Some(k) = &d.track {
+                                                    let [vx, vy, _] = k.position_variance();
+                                                    let text = format!(
+                                                        "σ:{:.1} m  rej:{}  rst:{}",
+                                                        vx.max(vy).sqrt(),
+                                                        k.rejected,
+                                                        k.resets
+                                                    );
+                                                    ui.label(RichText::new(text).monospace())
+                                                        .on_hover_text(
+                                                            "Kalman position error, outliers \
+                                                             rejected and track restarts",
+                                                        );
+                                                }
                                             });
 
+                                        // Geofence breaches
+                                        for b in &d.breaches {
+                                            let secs = now.saturating_duration_since(b.since).as_secs_f32();
+                                            ui.label(
+                                                RichText::new(format!(
+                                                    "⚠ {} {} · {secs:.0} s",
+                                                    b.kind.label(),
+                                                    b.fence
+                                                ))
+                                                .color(Color32::from_rgb(255, 130, 130)),
+                                            );
+                                        }
+
                                         ui.add_space(6.0);
 
                                         // Actions
@@ -726,7 +2791,8 @@ impl eframe::App for App {
                                                 self.hud_expanded = true;
                                             }
                                             if ui.button("Center on drone").clicked() {
-                                                // Hook: when you add pan/zoom camera, jump to this drone
+                                                self.camera.center = Vec2::new(d.smoothed_x, d.smoothed_y);
+                                                self.camera.set_mode(CameraMode::Follow);
                                             }
                                         });
                                     } else {
@@ -785,101 +2851,176 @@ impl eframe::App for App {
                     if let Some(id) = self.selected {
                         let snap = {
                             let guard = self.state.lock().unwrap();
-                            guard.drones.get(&id).cloned()
+                            let metric = guard.home.is_some();
+                            guard.drones.get(&id).cloned().map(|d| (guard.now(), metric, d))
                         };
-                        if let Some(d) = snap {
-                            // Bigger tiles for the modal
-                            let gap = 10.0;
-                            let ring_h = 170.0;
-                            let ring_w = 170.0;
+                        if let Some((now, metric, d)) = snap {
+                            // Instantaneous values on the left, history charts on the right
+                            ui.horizontal_top(|ui| {
+                                ui.vertical(|ui| {
+                                    // Bigger tiles for the modal
+                                    let gap = 10.0;
+                                    let ring_h = 170.0;
+                                    let ring_w = 170.0;
+
+                                    ui.add_space(6.0);
+                                    ui.horizontal(|ui| {
+                                        glass_card(ui, Vec2::new(ring_w, ring_h), |ui, rect| {
+                                            let p = ui.painter_at(rect);
+                                            let v = (d.battery / 100.0).clamp(0.0, 1.0);
+                                            let col = if d.battery < LOW_BATTERY_PCT {
+                                                Color32::from_rgb(255, 110, 110)
+                                            } else {
+                                                Color32::from_rgb(120, 220, 160)
+                                            };
+                                            draw_ring_gauge(
+                                                &p,
+                                                rect,
+                                                v,
+                                                col,
+                                                Color32::from_rgba_unmultiplied(255, 255, 255, 26),
+                                                &format!("{:>3.0}%", d.battery),
+                                                "Battery",
+                                            );
+                                        });
+                                        ui.add_space(gap);
+                                        let age = now.saturating_duration_since(d.last_seen);
+                                        glass_card(ui, Vec2::new(ring_w, ring_h), |ui, rect| {
+                                            let p = ui.painter_at(rect);
+                                            let secs = age.as_secs_f32();
+                                            let freshness = (1.0 - (secs / 5.0)).clamp(0.0, 1.0);
+                                            let col = if secs > 2.0 {
+                                                Color32::from_rgb(255, 200, 120)
+                                            } else {
+                                                Color32::from_rgb(140, 190, 255)
+                                            };
+                                            draw_ring_gauge(
+                                                &p,
+                                                rect,
+                                                freshness,
+                                                col,
+                                                Color32::from_rgba_unmultiplied(255, 255, 255, 26),
+                                                &if age < Duration::from_secs(1) {
+                                                    format!("{} ms", age.as_millis())
+                                                } else {
+                                                    format!("{:.1} s", secs)
+                                                },
+                                                "Last pkt",
+                                            );
+                                        });
+                                    });
+
+                                    ui.add_space(8.0);
+
+                                    // Numeric tiles row
+                                    ui.horizontal(|ui| {
+                                        numeric_tile_wh(ui, "Altitude", &format!("{:>6.1} m", d.z), 160.0, 84.0);
+                                        ui.add_space(8.0);
+                                        // Prefer the reported or Kalman speed; estimate from the
+                                        // trail otherwise
+                                        let speed = if let Some(v) = d.speed() {
+                                            v
+                                        } else if d.trail.len() >= 2 {
+                                            let (x2, y2, t2) = d.trail.back().copied().unwrap();
+                                            let (x1, y1, t1) = d.trail.get(d.trail.len() - 2).copied().unwrap();
+                                            let dt = (t2 - t1).as_secs_f32().max(1e-3);
+                                            let dx = x2 - x1;
+                                            let dy = y2 - y1;
+                                            (dx * dx + dy * dy).sqrt() / dt
+                                        } else {
+                                            0.0
+                                        };
+                                        let speed = format!("{speed:>6.2} {}", speed_unit(metric));
+                                        numeric_tile_wh(ui, "Speed", &speed, 160.0, 84.0);
+                                    });
+
+                                    // Attitude / navigation tiles (optional fields)
+                                    if d.course_deg().is_some() || d.pitch.is_some() || d.gps_fix.is_some()
+                                    {
+                                        ui.add_space(8.0);
+                                        ui.horizontal(|ui| {
+                                            let heading = d
+                                                .course_deg()
+                                                .map_or("--".to_string(), |h| format!("{h:>5.1}°"));
+                                            numeric_tile_wh(ui, "Heading", &heading, 104.0, 84.0);
+                                            ui.add_space(8.0);
+                                            let attitude = match (d.pitch, d.roll) {
+                                                (Some(p), Some(r)) => format!("{p:+.0}° / {r:+.0}°"),
+                                                _ => "--".to_string(),
+                                            };
+                                            numeric_tile_wh(ui, "Pitch / Roll", &attitude, 104.0, 84.0);
+                                            ui.add_space(8.0);
+                                            let gps = match (d.gps_fix, d.satellites) {
+                                                (Some(f), Some(n)) => format!("{} ·{n}", f.label()),
+                                                (Some(f), None) => f.label().to_string(),
+                                                (None, Some(n)) => format!("{n} sats"),
+                                                (None, None) => "--".to_string(),
+                                            };
+                                            numeric_tile_wh(ui, "GPS", &gps, 104.0, 84.0);
+                                        });
+                                    }
 
-                            ui.add_space(6.0);
-                            ui.horizontal(|ui| {
-                                glass_card(ui, Vec2::new(ring_w, ring_h), |ui, rect| {
-                                    let p = ui.painter_at(rect);
-                                    let v = (d.battery / 100.0).clamp(0.0, 1.0);
-                                    let col = if d.battery < 15.0 {
-                                        Color32::from_rgb(255, 110, 110)
-                                    } else {
-                                        Color32::from_rgb(120, 220, 160)
-                                    };
-                                    draw_ring_gauge(
-                                        &p,
-                                        rect,
-                                        v,
-                                        col,
-                                        Color32::from_rgba_unmultiplied(255, 255, 255, 26),
-                                        &format!("{:>3.0}%", d.battery),
-                                        "Battery",
-                                    );
-                                });
-                                ui.add_space(gap);
-                                let age = d.last_seen.elapsed();
-                                glass_card(ui, Vec2::new(ring_w, ring_h), |ui, rect| {
-                                    let p = ui.painter_at(rect);
-                                    let secs = age.as_secs_f32();
-                                    let freshness = (1.0 - (secs / 5.0)).clamp(0.0, 1.0);
-                                    let col = if secs > 2.0 {
-                                        Color32::from_rgb(255, 200, 120)
-                                    } else {
-                                        Color32::from_rgb(140, 190, 255)
-                                    };
-                                    draw_ring_gauge(
-                                        &p,
-                                        rect,
-                                        freshness,
-                                        col,
-                                        Color32::from_rgba_unmultiplied(255, 255, 255, 26),
-                                        &if age < Duration::from_secs(1) {
-                                            format!("{} ms", age.as_millis())
+                                    // Link health tiles
+                                    let link = &d.link;
+                                    ui.add_space(8.0);
+                                    ui.horizontal(|ui| {
+                                        let rate = format!("{:.1} Hz", link.rate_hz(now));
+                                        numeric_tile_wh(ui, "Rate", &rate, 104.0, 84.0);
+                                        ui.add_space(8.0);
+                                        let loss = if link.sequenced() {
+                                            format!("{:.1}% ·{}", link.loss_ratio() * 100.0, link.lost)
                                         } else {
-                                            format!("{:.1} s", secs)
-                                        },
-                                        "Last pkt",
-                                    );
+                                            "--".to_string()
+                                        };
+                                        numeric_tile_wh(ui, "Loss", &loss, 104.0, 84.0);
+                                        ui.add_space(8.0);
+                                        let quality = format!("{:.0}%", link.quality() * 100.0);
+                                        numeric_tile_wh(ui, "Link quality", &quality, 104.0, 84.0);
+                                    });
+                                    ui.add_space(8.0);
+                                    ui.horizontal(|ui| {
+                                        let jitter = format!("{:.1} ms", link.jitter_ms);
+                                        numeric_tile_wh(ui, "Jitter", &jitter, 104.0, 84.0);
+                                        ui.add_space(8.0);
+                                        let offset = format!("{:+.0} ms", link.clock_offset_ms);
+                                        numeric_tile_wh(ui, "Clock offset", &offset, 104.0, 84.0);
+                                        ui.add_space(8.0);
+                                        let reorder = format!(
+                                            "{} / {} / {}",
+                                            link.out_of_order, d.late, link.duplicates
+                                        );
+                                        let title = "Reord/Late/Dup";
+                                        numeric_tile_wh(ui, title, &reorder, 104.0, 84.0);
+                                    });
+
+                                    ui.add_space(8.0);
+
+                                    // Position card
+                                    glass_card(ui, Vec2::new(360.0, 96.0), |ui, rect| {
+                                        let p = ui.painter_at(rect);
+                                        p.text(
+                                            rect.left_top(),
+                                            egui::Align2::LEFT_TOP,
+                                            "Position",
+                                            FontId::proportional(13.0),
+                                            Color32::from_rgb(190, 200, 215),
+                                        );
+                                        let txt =
+                                            format!("x = {:>7.2}\ny = {:>7.2}\nz = {:>7.2}", d.x, d.y, d.z);
+                                        p.text(
+                                            rect.left_top() + Vec2::new(0.0, 20.0),
+                                            egui::Align2::LEFT_TOP,
+                                            txt,
+                                            FontId::monospace(16.0),
+                                            Color32::from_rgb(235, 240, 248),
+                                        );
+                                    });
+                                });
+                                ui.add_space(16.0);
+                                ui.vertical(|ui| {
+                                    ui.set_width(520.0);
+                                    self.history_charts(ui);
                                 });
-                            });
-
-                            ui.add_space(8.0);
-
-                            // Numeric tiles row
-                            ui.horizontal(|ui| {
-                                numeric_tile_wh(ui, "Altitude", &format!("{:>6.1} m", d.z), 160.0, 84.0);
-                                ui.add_space(8.0);
-                                let speed = if d.trail.len() >= 2 {
-                                    let (x2, y2, t2) = d.trail.back().copied().unwrap();
-                                    let (x1, y1, t1) = d.trail.get(d.trail.len() - 2).copied().unwrap();
-                                    let dt = (t2 - t1).as_secs_f32().max(1e-3);
-                                    let dx = x2 - x1;
-                                    let dy = y2 - y1;
-                                    (dx * dx + dy * dy).sqrt() / dt
-                                } else {
-                                    0.0
-                                };
-                                numeric_tile_wh(ui, "Speed", &format!("{:>6.2} u/s", speed), 160.0, 84.0);
-                            });
-
-                            ui.add_space(8.0);
-
-                            // Position card
-                            glass_card(ui, Vec2::new(360.0, 96.0), |ui, rect| {
-                                let p = ui.painter_at(rect);
-                                p.text(
-                                    rect.left_top(),
-                                    egui::Align2::LEFT_TOP,
-                                    "Position",
-                                    FontId::proportional(13.0),
-                                    Color32::from_rgb(190, 200, 215),
-                                );
-                                let txt =
-                                    format!("x = {:>7.2}\ny = {:>7.2}\nz = {:>7.2}", d.x, d.y, d.z);
-                                p.text(
-                                    rect.left_top() + Vec2::new(0.0, 20.0),
-                                    egui::Align2::LEFT_TOP,
-                                    txt,
-                                    FontId::monospace(16.0),
-                                    Color32::from_rgb(235, 240, 248),
-                                );
                             });
                         } else {
                             ui.label("No recent packets.");
@@ -890,6 +3031,195 @@ impl eframe::App for App {
                 });
             self.hud_expanded = open;
         }
+
+        /* ------------------- geofences: zones, drawing, breach log ------------------- */
+        if self.show_fences {
+            let mut open = self.show_fences;
+            egui::Window::new("Geofences")
+                .open(&mut open)
+                .default_width(380.0)
+                .resizable(true)
+                .show(ctx, |ui| {
+                    let mut state = self.state.lock().unwrap();
+                    let now = state.now();
+
+                    // Zone list
+                    let mut remove = None;
+                    egui::Grid::new("fence_list")
+                        .striped(true)
+                        .num_columns(5)
+                        .show(ui, |ui| {
+                            for (i, f) in state.fences.iter().enumerate() {
+                                ui.label(RichText::new("■").color(fence_color(f.kind, 230)));
+                                ui.label(&f.name);
+                                ui.label(match &f.shape {
+                                    FenceShape::Circle { radius, .. } => format!("circle r {radius:.0}"),
+                                    FenceShape::Polygon(pts) => format!("polygon {}", pts.len()),
+                                });
+                                let band = match (f.floor, f.ceiling) {
+                                    (Some(lo), Some(hi)) => format!("z {lo:.0}..{hi:.0}"),
+                                    (Some(lo), None) => format!("z ≥ {lo:.0}"),
+                                    (None, Some(hi)) => format!("z ≤ {hi:.0}"),
+                                    (None, None) => String::new(),
+                                };
+                                ui.label(RichText::new(band).small());
+                                if ui.small_button("🗑").on_hover_text("Delete zone").clicked() {
+                                    remove = Some(i);
+                                }
+                                ui.end_row();
+                            }
+                        });
+                    if state.fences.is_empty() {
+                        ui.label(RichText::new("No zones yet.").small());
+                    }
+                    if let Some(i) = remove {
+                        let mut fences = state.fences.clone();
+                        fences.remove(i);
+                        state.set_fences(fences);
+                    }
+
+                    ui.separator();
+
+                    // Settings for the next zone, then draw it on the map
+                    let t = &mut self.fence_template;
+                    ui.horizontal(|ui| {
+                        ui.label("Name");
+                        ui.text_edit_singleline(&mut t.name);
+                    });
+                    ui.horizontal(|ui| {
+                        ui.selectable_value(&mut t.kind, FenceKind::Exclusion, "Keep out");
+                        ui.selectable_value(&mut t.kind, FenceKind::Inclusion, "Keep in");
+                        ui.separator();
+                        ui.selectable_value(&mut t.circle, false, "Polygon");
+                        ui.selectable_value(&mut t.circle, true, "Circle");
+                    });
+                    ui.horizontal(|ui| {
+                        for (label, value, default) in
+                            [("Floor", &mut t.floor, 0.0), ("Ceiling", &mut t.ceiling, 120.0)]
+                        {
+                            let mut on = value.is_some();
+                            ui.checkbox(&mut on, label);
+                            match (on, value.as_mut()) {
+                                (true, Some(v)) => {
+                                    ui.add(egui::DragValue::new(v).speed(1.0));
+                                }
+                                (true, None) => *value = Some(default),
+                                (false, _) => *value = None,
+                            }
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        if let Some(draft) = &self.fence_draft {
+                            ui.label(
+                                RichText::new(if draft.circle {
+                                    "Click the center, then a point on the rim."
+                                } else {
+                                    "Click to add vertices, double-click the last one."
+                                })
+                                .small(),
+                            );
+                            if ui.button("Cancel").clicked() {
+                                self.fence_draft = None;
+                            }
+                        } else if ui.button("✏ Draw on map").clicked() {
+                            let mut draft = t.clone();
+                            if draft.name.trim().is_empty() {
+                                draft.name = format!("Zone {}", state.fences.len() + 1);
+                            }
+                            draft.points.clear();
+                            self.fence_draft = Some(draft);
+                            t.name.clear();
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        if ui.button("Save").clicked() {
+                            self.fences_status = Some(match save_fences(&self.fences_path, &state.fences) {
+                                Ok(()) => format!("Saved to {}", self.fences_path.display()),
+                                Err(e) => e.to_string(),
+                            });
+                        }
+                        if let Some(status) = &self.fences_status {
+                            ui.label(RichText::new(status).small());
+                        }
+                    });
+
+                    ui.separator();
+
+                    // Breach log, newest first; clicking a row selects the drone
+                    ui.label(RichText::new("Breaches").strong());
+                    egui::ScrollArea::vertical()
+                        .max_height(220.0)
+                        .auto_shrink([false, true])
+                        .show(ui, |ui| {
+                            if state.fence_events.is_empty() {
+                                ui.label(RichText::new("None so far.").small());
+                            }
+                            for e in state.fence_events.iter().rev() {
+                                let age = now.saturating_duration_since(e.at).as_secs_f32();
+                                let text = format!(
+                                    "{age:>6.0} s ago  #{:<4} {} {} {}",
+                                    e.drone,
+                                    if e.cleared { "back from" } else { "is" },
+                                    e.kind.label(),
+                                    e.fence
+                                );
+                                let color = if e.cleared {
+                                    Color32::from_rgb(170, 180, 195)
+                                } else {
+                                    Color32::from_rgb(255, 130, 130)
+                                };
+                                let row = ui.selectable_label(
+                                    self.selected == Some(e.drone),
+                                    RichText::new(text).monospace().small().color(color),
+                                );
+                                if row.clicked() {
+                                    self.selected = Some(e.drone);
+                                }
+                            }
+                        });
+                });
+            self.show_fences = open;
+        }
+
+        /* ---------------------- diagnostics: rejected datagrams ---------------------- */
+        if self.show_diagnostics {
+            let mut open = self.show_diagnostics;
+            egui::Window::new("Diagnostics")
+                .open(&mut open)
+                .default_width(560.0)
+                .resizable(true)
+                .show(ctx, |ui| self.diagnostics_ui(ui));
+            self.show_diagnostics = open;
+        }
+
+        /* ------------------------ alerts: floating window ------------------------ */
+        if self.show_alerts && !self.alerts_docked {
+            let mut open = self.show_alerts;
+            egui::Window::new("Alerts")
+                .open(&mut open)
+                .default_width(680.0)
+                .resizable(true)
+                .show(ctx, |ui| self.alerts_ui(ui));
+            self.show_alerts = open;
+        }
+
+        // Pulse the window edge in the alert's color while a new alert is flashing
+        if let Some((at, severity)) = self.flash {
+            let t = at.elapsed().as_secs_f32() / ALERT_FLASH.as_secs_f32();
+            if t < 1.0 {
+                let pulse = 0.5 + 0.5 * (t * 6.0 * std::f32::consts::PI).cos();
+                let [r, g, b, _] = severity_color(severity).to_array();
+                let color = Color32::from_rgba_unmultiplied(r, g, b, (pulse * (1.0 - t) * 255.0) as u8);
+                let layer = egui::LayerId::new(egui::Order::Foreground, Id::new("alert_flash"));
+                ctx.layer_painter(layer).rect_stroke(
+                    ctx.screen_rect().shrink(3.0),
+                    0.0,
+                    Stroke::new(6.0, color),
+                );
+            } else {
+                self.flash = None;
+            }
+        }
     }
 }
 
@@ -898,8 +3228,138 @@ impl eframe::App for App {
 fn main() -> eframe::Result<()> {
     let args = Args::parse();
 
-    let shared = Arc::new(Mutex::new(AppState::default()));
-    spawn_udp_listener(args.bind.clone(), shared.clone());
+    let fences = match &args.geofences {
+        Some(path) if path.exists() => match load_fences(path) {
+            Ok(fences) => {
+                println!("dashboard: {} geofences from {}", fences.len(), path.display());
+                fences
+            }
+            Err(e) => {
+                eprintln!("dashboard: {}: {e}", path.display());
+                std::process::exit(1);
+            }
+        },
+        // A new file: start empty and create it on Save
+        _ => Vec::new(),
+    };
+
+    let rules = match &args.alerts {
+        Some(path) => match load_rules(path) {
+            Ok(rules) => {
+                println!("dashboard: {} alert rules from {}", rules.len(), path.display());
+                rules
+            }
+            Err(e) => {
+                eprintln!("dashboard: {}: {e}", path.display());
+                std::process::exit(1);
+            }
+        },
+        None => default_rules(),
+    };
+    let operator = args
+        .operator
+        .clone()
+        .or_else(|| std::env::var("USER").ok())
+        .unwrap_or_else(|| "operator".to_string());
+
+    if !(args.stale_after > 0.0 && args.lost_after > args.stale_after) {
+        eprintln!("dashboard: need 0 < --stale-after < --lost-after");
+        std::process::exit(1);
+    }
+    let lifecycle = LifecycleConfig {
+        stale_after: Duration::from_secs_f32(args.stale_after),
+        lost_after: Duration::from_secs_f32(args.lost_after),
+        remove_lost: args.remove_lost,
+    };
+
+    if !(args.accel_noise > 0.0 && args.position_noise > 0.0) {
+        eprintln!("dashboard: --accel-noise and --position-noise must be positive");
+        std::process::exit(1);
+    }
+    if !(args.predict_horizon >= 0.0 && args.predict_horizon.is_finite()) {
+        eprintln!("dashboard: --predict-horizon must be a non-negative number of seconds");
+        std::process::exit(1);
+    }
+    let tracking = TrackConfig {
+        estimator: args.estimator,
+        accel_noise: args.accel_noise,
+        position_noise: args.position_noise,
+        predict_horizon: Duration::from_secs_f32(args.predict_horizon),
+        ..Default::default()
+    };
+
+    let shared = Arc::new(Mutex::new(AppState {
+        home: args.home.map(LocalFrame::new),
+        fences,
+        alerts: AlertEngine::new(rules),
+        lifecycle,
+        policy: args.fusion,
+        tracking,
+        ..Default::default()
+    }));
+
+    let mut recording = Recording {
+        path: args.record.clone(),
+        ..Default::default()
+    };
+    if recording.path.is_some() {
+        recording.start();
+    }
+    let recording = Arc::new(Mutex::new(recording));
+
+    let replay = match &args.replay {
+        Some(path) => match Replay::load(path) {
+            Ok(r) => {
+                println!(
+                    "dashboard: replaying {} packets from {} ({} undecodable records skipped)",
+                    r.len(),
+                    path.display(),
+                    r.skipped
+                );
+                Some(r)
+            }
+            Err(e) => {
+                eprintln!("dashboard: cannot open {}: {e}", path.display());
+                std::process::exit(1);
+            }
+        },
+        None => {
+            // Bind every listener before starting any, so a bad address stops the
+            // dashboard instead of leaving it half deaf
+            let sockets: Vec<UdpSocket> = args
+                .bind
+                .iter()
+                .map(|spec| {
+                    bind_udp(&spec.addr).unwrap_or_else(|e| {
+                        eprintln!("dashboard: cannot listen on {}: {e}", spec.addr);
+                        std::process::exit(1);
+                    })
+                })
+                .collect();
+            for (spec, socket) in args.bind.iter().zip(sockets) {
+                println!("dashboard: listening on {} (JSON and binary frames)", spec.addr);
+                let source = shared.lock().unwrap().add_source(&spec.addr, spec.weight);
+                spawn_udp_listener(socket, source, shared.clone(), recording.clone());
+            }
+            None
+        }
+    };
+
+    let tiles = args.tiles.as_ref().map(|path| match TileLayer::open(path) {
+        Ok(layer) => {
+            println!(
+                "dashboard: map tiles from {} (zoom {}..={})",
+                path.display(),
+                layer.zooms.0,
+                layer.zooms.1
+            );
+            layer
+        }
+        Err(e) => {
+            eprintln!("dashboard: {e}");
+            std::process::exit(1);
+        }
+    });
 
     let native_options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
@@ -912,6 +3372,16 @@ fn main() -> eframe::Result<()> {
     eframe::run_native(
         "Telemetry Fusion Dashboard",
         native_options,
-        Box::new(move |_| Box::new(App::new(shared.clone(), args.world_extent))),
+        Box::new(move |_| {
+            Box::new(App::new(
+                shared.clone(),
+                recording.clone(),
+                replay,
+                args.world_extent,
+                tiles,
+                args.geofences,
+                operator,
+            ))
+        }),
     )
 }
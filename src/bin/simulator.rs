@@ -1,9 +1,17 @@
-use clap::Parser;
-use rand::Rng;
-use serde::Serialize;
+use clap::{Parser, Subcommand};
+use rand::{rngs::StdRng, Rng, SeedableRng};
 use std::net::UdpSocket;
+use std::path::{Path, PathBuf};
 use std::thread;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant};
+use telemetry_fusion_dashboard::{
+    flight::{FlightParams, FlightState, Wind},
+    geo::{Geodetic, LocalFrame},
+    record::read_log,
+    scenario::{Scenario, ScenarioRun},
+    telemetry::{now_ms, GpsFix, Telemetry, LOW_BATTERY_PCT},
+    wire::WireFormat,
+};
 
 #[derive(Parser, Debug)]
 #[command(name = "simulator", about = "Fake drone telemetry UDP broadcaster")]
@@ -13,7 +21,7 @@ struct Args {
     drones: u32,
 
     /// Target UDP address (host:port)
-    #[arg(short, long, default_value = "127.0.0.1:5000")]
+    #[arg(short, long, global = true, default_value = "127.0.0.1:5000")]
     target: String,
 
     /// Send interval in milliseconds
@@ -23,71 +31,347 @@ struct Args {
     /// Initial spread radius for x/y (world units)
     #[arg(long, default_value_t = 100.0)]
     spread: f32,
+
+    /// Wire encoding for each packet
+    #[arg(long, value_enum, default_value_t = WireFormat::Json)]
+    format: WireFormat,
+
+    /// Run a scripted scenario file (JSON) instead of random flights
+    #[arg(long, value_name = "PATH")]
+    scenario: Option<PathBuf>,
+
+    /// Seed for all randomness (positions, routes, wind, battery noise, packet loss)
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Stop after this many seconds of simulated time
+    #[arg(long, value_name = "SECS")]
+    duration: Option<f64>,
+
+    /// Stop after this many send ticks
+    #[arg(long)]
+    ticks: Option<u64>,
+
+    /// Stamp packets from a simulated clock starting at this ts_ms instead of wall time
+    #[arg(long, value_name = "MS")]
+    start_ms: Option<u64>,
+
+    /// Steady wind as east,north velocity in units/s
+    #[arg(long, value_parser = parse_pair, allow_hyphen_values = true, value_name = "E,N")]
+    wind: Option<[f32; 2]>,
+
+    /// Standard deviation of wind gusts in units/s
+    #[arg(long, default_value_t = 0.0)]
+    gust: f32,
+
+    /// Also send WGS84 lat/lon/alt, treating x/y/z as metres east/north/up of this
+    /// home point (LAT,LON[,ALT])
+    #[arg(long, value_name = "LAT,LON[,ALT]", allow_hyphen_values = true)]
+    home: Option<Geodetic>,
+
+    /// Fraction of packets to drop before sending (0.0..=1.0)
+    #[arg(long, default_value_t = 0.0)]
+    packet_loss: f64,
+
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
-#[derive(Serialize, Clone)]
-struct Telemetry {
-    id: u32,
-    x: f32,
-    y: f32,
-    z: f32,
-    battery: f32,
-    status: String,
-    ts_ms: u128,
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Re-send a recorded session log with its original packet timing
+    Replay {
+        /// Session log written by `dashboard --record`
+        log: PathBuf,
+
+        /// Playback speed multiplier (2.0 = twice as fast)
+        #[arg(short, long, default_value_t = 1.0)]
+        speed: f64,
+
+        /// Start over from the beginning when the log ends
+        #[arg(short = 'l', long = "loop")]
+        looping: bool,
+    },
 }
 
-fn now_ms() -> u128 {
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_millis()
+fn parse_pair(s: &str) -> Result<[f32; 2], String> {
+    let (a, b) = s.split_once(',').ok_or("expected two comma-separated numbers")?;
+    let parse = |v: &str| v.trim().parse::<f32>().map_err(|e| e.to_string());
+    Ok([parse(a)?, parse(b)?])
+}
+
+impl Args {
+    /// Number of ticks to run, if bounded by `--ticks` and/or `--duration`.
+    fn tick_limit(&self) -> Option<u64> {
+        let from_duration = self
+            .duration
+            .map(|secs| (secs * 1000.0 / self.interval_ms.max(1) as f64).ceil() as u64);
+        match (self.ticks, from_duration) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    /// `ts_ms` for the given tick: simulated if `--start-ms` is set, wall time otherwise.
+    fn stamp(&self, tick: u64) -> u128 {
+        match self.start_ms {
+            Some(start) => start as u128 + tick as u128 * self.interval_ms as u128,
+            None => now_ms(),
+        }
+    }
+
+    fn wind(&self) -> Wind {
+        Wind {
+            mean: self.wind.unwrap_or_default(),
+            gust: self.gust,
+        }
+    }
+
+    fn home_frame(&self) -> Option<LocalFrame> {
+        self.home.map(LocalFrame::new)
+    }
+
+    fn rng(&self) -> StdRng {
+        match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        }
+    }
 }
 
 fn main() -> std::io::Result<()> {
     let args = Args::parse();
+    if !(0.0..=1.0).contains(&args.packet_loss) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--packet-loss must be between 0 and 1",
+        ));
+    }
 
     let sock = UdpSocket::bind("0.0.0.0:0")?;
     sock.connect(&args.target)?;
+
+    match &args.command {
+        Some(Command::Replay {
+            log,
+            speed,
+            looping,
+        }) => run_replay(&sock, &args.target, log, *speed, *looping),
+        None => match &args.scenario {
+            Some(path) => run_scenario(&sock, &args, path),
+            None => run_random_flights(&sock, &args),
+        },
+    }
+}
+
+/// Add lat/lon/alt matching the packet's x/y/z, read as ENU metres from `home`.
+fn geotag(t: &mut Telemetry, home: &LocalFrame) {
+    let g = home.from_enu([t.x as f64, t.y as f64, t.z as f64]);
+    t.lat = Some(g.lat);
+    t.lon = Some(g.lon);
+    t.alt = Some(g.alt as f32);
+}
+
+/// Execute a scenario file, one simulation step per send interval.
+fn run_scenario(sock: &UdpSocket, args: &Args, path: &Path) -> std::io::Result<()> {
+    let mut scenario = Scenario::load(path)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?;
+    scenario.wind.get_or_insert_with(|| args.wind());
+    let mut run = ScenarioRun::new(scenario, args.seed.unwrap_or(0));
+    println!(
+        "simulator: running scenario {} ({} drones) to {} every {} ms ({:?})",
+        path.display(),
+        run.drone_count(),
+        args.target,
+        args.interval_ms,
+        args.format
+    );
+
+    let mut rng = args.rng();
+    let home = args.home_frame();
+    let interval = Duration::from_millis(args.interval_ms);
+    let dt = interval.as_secs_f64();
+    let limit = args.tick_limit();
+
+    let mut tick = 0;
+    while !run.finished() && limit.is_none_or(|n| tick < n) {
+        for mut t in run.step(dt, args.stamp(tick)) {
+            if args.packet_loss > 0.0 && rng.gen_bool(args.packet_loss) {
+                continue;
+            }
+            if let Some(home) = &home {
+                geotag(&mut t, home);
+            }
+            sock.send(&t.encode(args.format))?;
+        }
+        tick += 1;
+        thread::sleep(interval);
+    }
+
+    println!("simulator: scenario finished after {:.1} s", run.elapsed_s());
+    Ok(())
+}
+
+/// Send raw datagrams from a session log, spaced by their recorded receive times.
+fn run_replay(
+    sock: &UdpSocket,
+    target: &str,
+    log: &Path,
+    speed: f64,
+    looping: bool,
+) -> std::io::Result<()> {
+    if speed.is_nan() || speed <= 0.0 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--speed must be positive",
+        ));
+    }
+
+    let records = read_log(log)?;
+    let Some(first) = records.first() else {
+        println!("simulator: {} contains no packets", log.display());
+        return Ok(());
+    };
+    let t0 = first.recv_ms;
     println!(
-        "simulator: sending {} drones to {} every {} ms",
-        args.drones, args.target, args.interval_ms
+        "simulator: replaying {} packets from {} to {} at {}x{}",
+        records.len(),
+        log.display(),
+        target,
+        speed,
+        if looping { " (looping)" } else { "" }
     );
 
-    // Initialize random positions and battery
-    let mut rng = rand::thread_rng();
-    let mut drones: Vec<Telemetry> = (0..args.drones)
-        .map(|id| Telemetry {
-            id,
-            x: rng.gen_range(-args.spread..args.spread),
-            y: rng.gen_range(-args.spread..args.spread),
-            z: rng.gen_range(0.0..50.0),
-            battery: rng.gen_range(60.0..100.0),
-            status: "OK".to_string(),
-            ts_ms: now_ms(),
+    loop {
+        let started = Instant::now();
+        for r in &records {
+            let offset_ms = r.recv_ms.saturating_sub(t0) as f64 / speed;
+            let due = started + Duration::from_secs_f64(offset_ms / 1000.0);
+            if let Some(wait) = due.checked_duration_since(Instant::now()) {
+                thread::sleep(wait);
+            }
+            sock.send(&r.payload)?;
+        }
+        if !looping {
+            return Ok(());
+        }
+    }
+}
+
+/// Battery drain at hover in random mode, percent per second.
+const HOVER_DRAIN_PCT_PER_S: f32 = 0.25;
+
+/// One randomly wandering drone: a flight model chasing random nearby waypoints.
+struct Wanderer {
+    id: u32,
+    flight: FlightState,
+    gust: [f32; 2],
+    target: [f32; 3],
+    cruise: f32,
+    battery: f32,
+    satellites: u8,
+    seq: u32,
+}
+
+fn random_target(rng: &mut StdRng, from: [f32; 3], spread: f32) -> [f32; 3] {
+    let bound = spread * 1.2;
+    [
+        (from[0] + rng.gen_range(-40.0..40.0)).clamp(-bound, bound),
+        (from[1] + rng.gen_range(-40.0..40.0)).clamp(-bound, bound),
+        rng.gen_range(10.0..80.0),
+    ]
+}
+
+fn run_random_flights(sock: &UdpSocket, args: &Args) -> std::io::Result<()> {
+    println!(
+        "simulator: sending {} drones to {} every {} ms ({:?})",
+        args.drones, args.target, args.interval_ms, args.format
+    );
+
+    // Initialize random positions, routes and battery
+    let mut rng = args.rng();
+    let mut drones: Vec<Wanderer> = (0..args.drones)
+        .map(|id| {
+            let pos = [
+                rng.gen_range(-args.spread..args.spread),
+                rng.gen_range(-args.spread..args.spread),
+                rng.gen_range(0.0..50.0),
+            ];
+            Wanderer {
+                id,
+                flight: FlightState::at(pos),
+                gust: [0.0, 0.0],
+                target: random_target(&mut rng, pos, args.spread),
+                cruise: rng.gen_range(3.0..8.0),
+                battery: rng.gen_range(60.0..100.0),
+                satellites: rng.gen_range(8..=14),
+                seq: 0,
+            }
         })
         .collect();
 
+    let params = FlightParams::default();
+    let wind = args.wind();
+    let home = args.home_frame();
     let interval = Duration::from_millis(args.interval_ms);
+    let dt = interval.as_secs_f32();
+    let limit = args.tick_limit();
 
-    loop {
+    let mut tick = 0;
+    while limit.is_none_or(|n| tick < n) {
         for d in &mut drones {
-            // Simple random walk
-            d.x += rng.gen_range(-1.5..1.5);
-            d.y += rng.gen_range(-1.5..1.5);
-            d.z = (d.z + rng.gen_range(-0.8..0.8)).clamp(0.0, 120.0);
+            let w = wind.sample(&mut d.gust, dt, &mut rng);
+            let left = d.flight.step_toward(d.target, d.cruise, &params, w, dt);
+            if left < 2.0 || rng.gen_bool(0.002) {
+                d.target = random_target(&mut rng, d.flight.pos, args.spread);
+                d.cruise = rng.gen_range(3.0..8.0);
+            }
 
-            // Battery slowly decreases; add tiny noise
-            d.battery = (d.battery - rng.gen_range(0.02..0.08)).max(0.0);
+            // Battery drain follows throttle; add a little noise
+            let noise = rng.gen_range(0.8..1.2);
+            d.battery = (d.battery - d.flight.drain(HOVER_DRAIN_PCT_PER_S, dt) * noise).max(0.0);
 
             // Status flips when low battery
-            d.status = if d.battery < 15.0 { "LOW_BAT".into() } else { "OK".into() };
+            let status = if d.battery < LOW_BATTERY_PCT { "LOW_BAT" } else { "OK" };
+
+            // Satellite count drifts slowly
+            if rng.gen_bool(0.02) {
+                d.satellites = (d.satellites as i32 + rng.gen_range(-1..=1)).clamp(4, 18) as u8;
+            }
+            let gps_fix = match d.satellites {
+                0..=3 => GpsFix::NoFix,
+                4..=5 => GpsFix::Fix2d,
+                _ => GpsFix::Fix3d,
+            };
 
-            d.ts_ms = now_ms();
+            let mut t = Telemetry {
+                id: d.id,
+                battery: d.battery,
+                status: status.to_string(),
+                ts_ms: args.stamp(tick),
+                gps_fix: Some(gps_fix),
+                satellites: Some(d.satellites),
+                seq: Some(d.seq),
+                ..Default::default()
+            };
+            d.flight.fill_telemetry(&mut t);
+            d.seq = d.seq.wrapping_add(1);
+            if let Some(home) = &home {
+                geotag(&mut t, home);
+            }
 
-            let payload = serde_json::to_vec(d).unwrap();
+            // Injected packet loss
+            if args.packet_loss > 0.0 && rng.gen_bool(args.packet_loss) {
+                continue;
+            }
+
+            let payload = t.encode(args.format);
             let _ = sock.send(&payload)?;
         }
 
+        tick += 1;
         thread::sleep(interval);
     }
+
+    Ok(())
 }
@@ -0,0 +1,154 @@
+//! Datagrams the dashboard could not use.
+//!
+//! Rejects are counted by reason and by sending address, and the latest few are
+//! kept with their payload so a new sender's mistakes can be read off the screen.
+
+use crate::telemetry::DecodeError;
+use std::collections::{BTreeMap, VecDeque};
+
+/// Rejected datagrams kept for inspection.
+pub const REJECTS_KEPT: usize = 50;
+
+/// Leading bytes of a rejected payload kept.
+pub const PAYLOAD_KEPT: usize = 512;
+
+/// A datagram that failed to decode or validate.
+#[derive(Debug, Clone)]
+pub struct Reject {
+    /// Running number, stable while the reject is kept.
+    pub id: u64,
+    /// Receive wall-clock time, ms since the Unix epoch.
+    pub wall_ms: u128,
+    /// Sending address ("ip:port").
+    pub sender: String,
+    pub error: DecodeError,
+    /// At most [`PAYLOAD_KEPT`] bytes of the datagram.
+    pub payload: Vec<u8>,
+    /// Full datagram length.
+    pub len: usize,
+}
+
+impl Reject {
+    /// The payload as text if it is UTF-8 (a cut-off character at the end is
+    /// dropped), as a hex dump otherwise.
+    pub fn preview(&self) -> String {
+        let text = match std::str::from_utf8(&self.payload) {
+            Ok(s) => Some(s),
+            // Only the truncation split a character
+            Err(e) if e.error_len().is_none() => {
+                std::str::from_utf8(&self.payload[..e.valid_up_to()]).ok()
+            }
+            Err(_) => None,
+        };
+        let mut out = match text {
+            Some(s) => s.to_string(),
+            None => self
+                .payload
+                .chunks(16)
+                .map(|row| row.iter().map(|b| format!("{b:02x}")).collect::<Vec<_>>().join(" "))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        };
+        if self.len > self.payload.len() {
+            out.push_str(&format!(" … ({} bytes)", self.len));
+        }
+        out
+    }
+}
+
+/// Reject counters and the latest rejects.
+#[derive(Debug, Clone, Default)]
+pub struct Diagnostics {
+    pub rejected: u64,
+    /// Counts per [`DecodeError::reason`].
+    pub by_reason: BTreeMap<String, u64>,
+    /// Counts per sending address.
+    pub by_sender: BTreeMap<String, u64>,
+    recent: VecDeque<Reject>,
+}
+
+impl Diagnostics {
+    /// Count a datagram from `sender` that failed with `error`.
+    pub fn record(&mut self, sender: &str, error: DecodeError, payload: &[u8], wall_ms: u128) {
+        self.rejected += 1;
+        *self.by_reason.entry(error.reason()).or_default() += 1;
+        *self.by_sender.entry(sender.to_string()).or_default() += 1;
+        self.recent.push_back(Reject {
+            id: self.rejected,
+            wall_ms,
+            sender: sender.to_string(),
+            error,
+            payload: payload[..payload.len().min(PAYLOAD_KEPT)].to_vec(),
+            len: payload.len(),
+        });
+        while self.recent.len() > REJECTS_KEPT {
+            self.recent.pop_front();
+        }
+    }
+
+    /// Latest rejects, oldest first, at most [`REJECTS_KEPT`].
+    pub fn recent(&self) -> &VecDeque<Reject> {
+        &self.recent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn out_of_range(field: &'static str) -> DecodeError {
+        DecodeError::OutOfRange { field, value: -1.0 }
+    }
+
+    #[test]
+    fn counts_by_reason_and_sender() {
+        let mut d = Diagnostics::default();
+        d.record("10.0.0.1:9000", out_of_range("battery"), b"{}", 1);
+        d.record("10.0.0.1:9000", out_of_range("battery"), b"{}", 2);
+        d.record("10.0.0.2:9000", DecodeError::Utf8, &[0xff], 3);
+
+        assert_eq!(d.rejected, 3);
+        assert_eq!(d.by_reason["battery out of range"], 2);
+        assert_eq!(d.by_reason["not UTF-8"], 1);
+        assert_eq!(d.by_sender["10.0.0.1:9000"], 2);
+        assert_eq!(d.by_sender["10.0.0.2:9000"], 1);
+        let last = d.recent().back().unwrap();
+        assert_eq!((last.id, last.wall_ms, last.sender.as_str()), (3, 3, "10.0.0.2:9000"));
+        assert_eq!(last.error, DecodeError::Utf8);
+    }
+
+    #[test]
+    fn keeps_only_the_latest_rejects() {
+        let mut d = Diagnostics::default();
+        for i in 0..REJECTS_KEPT as u128 + 10 {
+            d.record("a", DecodeError::Utf8, b"x", i);
+        }
+        assert_eq!(d.recent().len(), REJECTS_KEPT);
+        assert_eq!(d.recent().front().unwrap().id, 11);
+        assert_eq!(d.recent().back().unwrap().id, REJECTS_KEPT as u64 + 10);
+        // Counters keep counting
+        assert_eq!(d.rejected, REJECTS_KEPT as u64 + 10);
+        assert_eq!(d.by_sender["a"], REJECTS_KEPT as u64 + 10);
+    }
+
+    #[test]
+    fn preview_truncates_text_and_dumps_binary() {
+        let mut d = Diagnostics::default();
+        // A two-byte character straddles the cut
+        let mut text = "a".repeat(PAYLOAD_KEPT - 1);
+        text.push_str("éxyz");
+        d.record("a", DecodeError::Utf8, text.as_bytes(), 0);
+        let reject = d.recent().back().unwrap();
+        assert_eq!(reject.payload.len(), PAYLOAD_KEPT);
+        assert_eq!(reject.len, PAYLOAD_KEPT + 4);
+        let expected = format!("{} … ({} bytes)", "a".repeat(PAYLOAD_KEPT - 1), PAYLOAD_KEPT + 4);
+        assert_eq!(reject.preview(), expected);
+
+        let binary: Vec<u8> = (0u8..18).map(|b| b.wrapping_mul(17)).collect();
+        d.record("a", DecodeError::Binary("bad magic"), &binary, 0);
+        assert_eq!(
+            d.recent().back().unwrap().preview(),
+            "00 11 22 33 44 55 66 77 88 99 aa bb cc dd ee ff\n10 21"
+        );
+    }
+}
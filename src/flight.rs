@@ -0,0 +1,363 @@
+//! Simple multirotor flight model used by the simulator.
+//!
+//! The model is kinematic with dynamic limits: a waypoint controller asks for a
+//! ground velocity, the airframe chases it within acceleration, speed, climb and
+//! yaw-rate limits, and wind is added on top. A throttle estimate derived from
+//! speed, climb and acceleration drives battery drain.
+
+use crate::telemetry::Telemetry;
+use rand::Rng;
+use serde::Deserialize;
+
+/// Throttle needed to hold a hover; battery drain is scaled relative to this.
+pub const HOVER_THROTTLE: f32 = 0.45;
+
+/// Airframe limits.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct FlightParams {
+    /// Max horizontal airspeed, units/s.
+    pub max_speed: f32,
+    /// Max horizontal acceleration, units/s².
+    pub max_accel: f32,
+    /// Max climb rate, units/s.
+    pub max_climb: f32,
+    /// Max descent rate, units/s.
+    pub max_descent: f32,
+    /// Max vertical acceleration, units/s².
+    pub max_vert_accel: f32,
+    /// Max yaw rate, degrees/s.
+    pub max_yaw_rate: f32,
+    /// Distance at which the controller starts slowing down for a waypoint.
+    pub arrive_radius: f32,
+}
+
+impl Default for FlightParams {
+    fn default() -> Self {
+        Self {
+            max_speed: 8.0,
+            max_accel: 3.0,
+            max_climb: 3.0,
+            max_descent: 2.0,
+            max_vert_accel: 2.0,
+            max_yaw_rate: 90.0,
+            arrive_radius: 6.0,
+        }
+    }
+}
+
+/// Horizontal wind: a steady component plus random gusts.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(default)]
+pub struct Wind {
+    /// Steady wind velocity (east, north), units/s.
+    pub mean: [f32; 2],
+    /// Standard deviation of the gust component, units/s.
+    pub gust: f32,
+}
+
+impl Wind {
+    /// Sample the wind for one step. Gusts are low-pass filtered in `gust_state`.
+    pub fn sample(&self, gust_state: &mut [f32; 2], dt: f32, rng: &mut impl Rng) -> [f32; 2] {
+        if self.gust > 0.0 {
+            // First-order Gauss-Markov process with a ~2 s correlation time
+            let k = (dt / 2.0).min(1.0);
+            for g in gust_state.iter_mut() {
+                *g += k * (gaussian(rng) * self.gust - *g);
+            }
+        }
+        [self.mean[0] + gust_state[0], self.mean[1] + gust_state[1]]
+    }
+}
+
+/// Dynamic state of one airframe.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FlightState {
+    pub pos: [f32; 3],
+    /// Ground velocity (east, north, up), units/s.
+    pub vel: [f32; 3],
+    /// Air-relative horizontal velocity, what the motors produce.
+    pub air_vel: [f32; 2],
+    /// Heading in radians, 0 = +y (north), clockwise positive.
+    pub heading: f32,
+    /// Last throttle estimate, 0..1.
+    pub throttle: f32,
+    /// Last horizontal acceleration (east, north), for attitude estimates.
+    pub accel: [f32; 2],
+}
+
+impl FlightState {
+    pub fn at(pos: [f32; 3]) -> Self {
+        Self {
+            pos,
+            throttle: if pos[2] > 0.0 { HOVER_THROTTLE } else { 0.0 },
+            ..Default::default()
+        }
+    }
+
+    pub fn ground_speed(&self) -> f32 {
+        self.vel[0].hypot(self.vel[1])
+    }
+
+    pub fn airborne(&self) -> bool {
+        self.pos[2] > 0.0
+    }
+
+    /// Fly toward `target` for `dt` seconds. `cruise` caps horizontal speed below the
+    /// airframe limit. Returns the distance left to the target.
+    pub fn step_toward(
+        &mut self,
+        target: [f32; 3],
+        cruise: f32,
+        params: &FlightParams,
+        wind: [f32; 2],
+        dt: f32,
+    ) -> f32 {
+        let dx = target[0] - self.pos[0];
+        let dy = target[1] - self.pos[1];
+        let dz = target[2] - self.pos[2];
+        let dist_h = dx.hypot(dy);
+
+        // Desired ground velocity: full cruise far away, proportional slow-down close in
+        let cruise = cruise.min(params.max_speed).max(0.0);
+        let want_speed = cruise * (dist_h / params.arrive_radius.max(0.1)).min(1.0);
+        let want_ground = if dist_h > 1e-3 {
+            [dx / dist_h * want_speed, dy / dist_h * want_speed]
+        } else {
+            [0.0, 0.0]
+        };
+
+        // Crab into the wind: the airframe has to supply ground - wind
+        let on_ground = !self.airborne() && dz <= 0.0;
+        let wind = if on_ground { [0.0, 0.0] } else { wind };
+        let mut want_air = [want_ground[0] - wind[0], want_ground[1] - wind[1]];
+        let air_mag = want_air[0].hypot(want_air[1]);
+        if air_mag > params.max_speed {
+            want_air[0] *= params.max_speed / air_mag;
+            want_air[1] *= params.max_speed / air_mag;
+        }
+        if on_ground {
+            want_air = [0.0, 0.0];
+        }
+
+        // Acceleration-limited airspeed change
+        let mut dvx = want_air[0] - self.air_vel[0];
+        let mut dvy = want_air[1] - self.air_vel[1];
+        let dv = dvx.hypot(dvy);
+        let max_dv = params.max_accel * dt;
+        if dv > max_dv {
+            dvx *= max_dv / dv;
+            dvy *= max_dv / dv;
+        }
+        self.air_vel[0] += dvx;
+        self.air_vel[1] += dvy;
+        self.accel = [dvx / dt.max(1e-3), dvy / dt.max(1e-3)];
+
+        // Vertical: rate-limited climb/descent with its own acceleration limit
+        let want_vz = (dz * 0.8).clamp(-params.max_descent, params.max_climb);
+        let max_dvz = params.max_vert_accel * dt;
+        let dvz = (want_vz - self.vel[2]).clamp(-max_dvz, max_dvz);
+        self.vel[2] += dvz;
+
+        self.vel[0] = self.air_vel[0] + wind[0];
+        self.vel[1] = self.air_vel[1] + wind[1];
+
+        self.pos[0] += self.vel[0] * dt;
+        self.pos[1] += self.vel[1] * dt;
+        self.pos[2] += self.vel[2] * dt;
+        if self.pos[2] <= 0.0 {
+            self.pos[2] = 0.0;
+            self.vel = [0.0, 0.0, self.vel[2].max(0.0)];
+            self.air_vel = [0.0, 0.0];
+        }
+
+        // Heading follows the air vector, limited by yaw rate
+        let air_speed = self.air_vel[0].hypot(self.air_vel[1]);
+        if air_speed > 0.3 {
+            let want_heading = self.air_vel[0].atan2(self.air_vel[1]);
+            let mut diff = want_heading - self.heading;
+            diff = (diff + std::f32::consts::PI).rem_euclid(std::f32::consts::TAU)
+                - std::f32::consts::PI;
+            let max_turn = params.max_yaw_rate.to_radians() * dt;
+            self.heading = (self.heading + diff.clamp(-max_turn, max_turn))
+                .rem_euclid(std::f32::consts::TAU);
+        }
+
+        self.throttle = if self.airborne() {
+            let speed_term = (air_speed / params.max_speed.max(0.1)).powi(2);
+            let climb_term = (self.vel[2] / params.max_climb.max(0.1)).max(0.0);
+            let accel_term = self.accel[0].hypot(self.accel[1]) / params.max_accel.max(0.1);
+            (HOVER_THROTTLE + 0.30 * speed_term + 0.20 * climb_term + 0.05 * accel_term)
+                .clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+
+        let rx = target[0] - self.pos[0];
+        let ry = target[1] - self.pos[1];
+        let rz = target[2] - self.pos[2];
+        (rx * rx + ry * ry + rz * rz).sqrt()
+    }
+
+    /// Heading in degrees, 0..360 clockwise from north.
+    pub fn heading_deg(&self) -> f32 {
+        self.heading.to_degrees().rem_euclid(360.0)
+    }
+
+    /// Pitch and roll in degrees: a multirotor tilts to accelerate and to push
+    /// against drag, so attitude follows acceleration plus airspeed in the body frame.
+    pub fn attitude_deg(&self) -> (f32, f32) {
+        const G: f32 = 9.81;
+        const DRAG: f32 = 0.3;
+        let (s, c) = self.heading.sin_cos();
+        let body = |v: [f32; 2]| (v[0] * s + v[1] * c, v[0] * c - v[1] * s);
+        let (acc_fwd, acc_right) = body(self.accel);
+        let (air_fwd, air_right) = body(self.air_vel);
+        let fwd = acc_fwd + DRAG * air_fwd;
+        let right = acc_right + DRAG * air_right;
+        // Nose down to go forward, right side down to go right
+        let pitch = -(fwd / G).atan().to_degrees();
+        let roll = (right / G).atan().to_degrees();
+        (pitch, roll)
+    }
+
+    /// Copy position and the optional kinematic fields into a packet.
+    pub fn fill_telemetry(&self, t: &mut Telemetry) {
+        let (pitch, roll) = self.attitude_deg();
+        [t.x, t.y, t.z] = self.pos;
+        t.vel = Some(self.vel);
+        t.ground_speed = Some(self.ground_speed());
+        t.heading = Some(self.heading_deg());
+        t.pitch = Some(pitch);
+        t.roll = Some(roll);
+    }
+
+    /// Battery charge consumed over `dt`, given the drain rate at hover.
+    pub fn drain(&self, hover_drain_per_s: f32, dt: f32) -> f32 {
+        hover_drain_per_s * (self.throttle / HOVER_THROTTLE) * dt
+    }
+}
+
+/// Standard normal sample (Box-Muller).
+fn gaussian(rng: &mut impl Rng) -> f32 {
+    let u1: f32 = rng.gen_range(f32::EPSILON..1.0);
+    let u2: f32 = rng.gen();
+    (-2.0 * u1.ln()).sqrt() * (std::f32::consts::TAU * u2).cos()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    const DT: f32 = 0.1;
+
+    #[test]
+    fn steps_respect_the_airframe_limits() {
+        let params = FlightParams::default();
+        let mut s = FlightState::at([0.0, 0.0, 10.0]);
+        for target in [[200.0, -50.0, 80.0], [-100.0, 20.0, 5.0]] {
+            for _ in 0..400 {
+                let before = s;
+                s.step_toward(target, 20.0, &params, [0.0, 0.0], DT);
+                let dv = (s.vel[0] - before.vel[0]).hypot(s.vel[1] - before.vel[1]);
+                assert!(dv <= params.max_accel * DT + 1e-4, "dv {dv}");
+                assert!(s.ground_speed() <= params.max_speed + 1e-4);
+                assert!(s.vel[2] <= params.max_climb + 1e-4, "climb {}", s.vel[2]);
+                assert!(s.vel[2] >= -params.max_descent - 1e-4, "descent {}", s.vel[2]);
+                let dvz = (s.vel[2] - before.vel[2]).abs();
+                assert!(dvz <= params.max_vert_accel * DT + 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn stops_at_the_waypoint() {
+        let params = FlightParams::default();
+        let target = [30.0, 40.0, 20.0];
+        let mut s = FlightState::at([0.0, 0.0, 10.0]);
+        let mut left = f32::MAX;
+        for _ in 0..600 {
+            left = s.step_toward(target, 6.0, &params, [0.0, 0.0], DT);
+        }
+        assert!(left < 0.5, "{left} from the waypoint");
+        assert!(s.ground_speed() < 0.1 && s.vel[2].abs() < 0.1);
+        assert!((s.throttle - HOVER_THROTTLE).abs() < 0.01);
+    }
+
+    #[test]
+    fn drain_grows_with_throttle_and_speed() {
+        let params = FlightParams::default();
+        let mut hover = FlightState::at([0.0, 0.0, 10.0]);
+        hover.step_toward([0.0, 0.0, 10.0], 0.0, &params, [0.0, 0.0], DT);
+        assert_eq!(hover.throttle, HOVER_THROTTLE);
+        assert!((hover.drain(0.05, 2.0) - 0.1).abs() < 1e-6);
+
+        // Cruising east at a steady speed, far from the waypoint
+        let cruising = |speed: f32| {
+            let mut s = FlightState {
+                vel: [speed, 0.0, 0.0],
+                air_vel: [speed, 0.0],
+                heading: std::f32::consts::FRAC_PI_2,
+                ..FlightState::at([0.0, 0.0, 10.0])
+            };
+            s.step_toward([1000.0, 0.0, 10.0], speed, &params, [0.0, 0.0], DT);
+            s.drain(0.05, 2.0)
+        };
+        let (slow, fast) = (cruising(4.0), cruising(8.0));
+        assert!(0.1 < slow && slow < fast, "{slow} {fast}");
+
+        let mut climbing = FlightState {
+            vel: [0.0, 0.0, 2.0],
+            ..FlightState::at([0.0, 0.0, 10.0])
+        };
+        climbing.step_toward([0.0, 0.0, 50.0], 0.0, &params, [0.0, 0.0], DT);
+        assert!(climbing.drain(0.05, 2.0) > 0.1);
+
+        let landed = FlightState::at([0.0, 0.0, 0.0]);
+        assert_eq!(landed.drain(0.05, 2.0), 0.0);
+    }
+
+    #[test]
+    fn steady_wind_pushes_a_hovering_drone_downwind() {
+        let params = FlightParams::default();
+        let wind = Wind {
+            mean: [3.0, 0.0],
+            gust: 0.0,
+        };
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut gusts = [0.0; 2];
+        let home = [0.0, 0.0, 10.0];
+        let mut s = FlightState::at(home);
+        let mut furthest = 0.0f32;
+        for _ in 0..600 {
+            let w = wind.sample(&mut gusts, DT, &mut rng);
+            assert_eq!(w, wind.mean);
+            s.step_toward(home, 5.0, &params, w, DT);
+            furthest = furthest.max(s.pos[0]);
+            assert!(s.pos[1].abs() < 1e-3);
+        }
+        // Blown east before the controller catches up, then holds by crabbing west
+        assert!(furthest > 0.5, "drifted {furthest}");
+        assert!(s.pos[0].abs() < 0.5 && s.ground_speed() < 0.1);
+        assert!((s.air_vel[0] + 3.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn gusts_average_out_to_the_mean() {
+        let wind = Wind {
+            mean: [2.0, -1.0],
+            gust: 1.5,
+        };
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut gusts = [0.0; 2];
+        let n = 20_000;
+        let mut sum = [0.0f32; 2];
+        for _ in 0..n {
+            let w = wind.sample(&mut gusts, DT, &mut rng);
+            sum = [sum[0] + w[0], sum[1] + w[1]];
+        }
+        let mean = sum.map(|s| s / n as f32);
+        assert!((mean[0] - 2.0).abs() < 0.2 && (mean[1] + 1.0).abs() < 0.2, "{mean:?}");
+    }
+}
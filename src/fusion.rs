@@ -0,0 +1,1369 @@
+use crate::{
+    alerts::{AlertEngine, Raised},
+    diagnostics::Diagnostics,
+    geo::{Geodetic, LocalFrame},
+    geofence::{BreachKind, Geofence},
+    link::LinkStats,
+    telemetry::{now_ms, GpsFix, Telemetry},
+    track::{Estimator, Kalman, TrackConfig, EMA_ALPHA},
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+// Trail pruning: keep a long history, bounded by size and age
+pub const TRAIL_MAX_POINTS: usize = 600;
+pub const TRAIL_MAX_AGE: Duration = Duration::from_secs(20);
+
+// Geofence events kept for the alerts list
+pub const FENCE_EVENTS_MAX: usize = 500;
+
+// Lifecycle transitions kept for the fleet panel
+pub const LIFECYCLE_EVENTS_MAX: usize = 500;
+
+/// Statuses meaning a drone is down on purpose: it stays "landed" instead of
+/// going stale or lost once it stops sending.
+pub const LANDED_STATUSES: [&str; 2] = ["LANDED", "RETIRED"];
+
+// Timestamps remembered per drone to drop copies relayed by another source
+pub const DEDUP_WINDOW: usize = 64;
+
+// Sender-time spread of the feeds averaged by the weighted policy, ms
+pub const WEIGHTED_SPAN_MS: u128 = 200;
+
+// Packets in a row stamped behind the latest, increasing and spread over at least
+// the stale timeout in both sender and arrival time, that mean the sender's clock
+// went back rather than that a buffered burst arrived late
+pub const CLOCK_RESET_PACKETS: u32 = 3;
+
+// Chart history: one sample per period; when full, every other sample is dropped
+// and the period doubles, so a whole session fits at decreasing resolution
+pub const HISTORY_MAX_SAMPLES: usize = 7200;
+pub const HISTORY_PERIOD: Duration = Duration::from_millis(500);
+
+/// Axis-aligned world-space box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bounds {
+    pub min: [f32; 2],
+    pub max: [f32; 2],
+}
+
+impl Bounds {
+    pub fn point(x: f32, y: f32) -> Self {
+        Self {
+            min: [x, y],
+            max: [x, y],
+        }
+    }
+
+    pub fn include(&mut self, x: f32, y: f32) {
+        self.min = [self.min[0].min(x), self.min[1].min(y)];
+        self.max = [self.max[0].max(x), self.max[1].max(y)];
+    }
+
+    pub fn width(&self) -> f32 {
+        self.max[0] - self.min[0]
+    }
+
+    pub fn height(&self) -> f32 {
+        self.max[1] - self.min[1]
+    }
+}
+
+/// Index of a telemetry source in [`AppState::sources`].
+pub type SourceId = usize;
+
+/// A receiver feeding the dashboard: a UDP listener live, the sending address
+/// during replay (logs don't record which listener got a packet).
+#[derive(Debug, Clone)]
+pub struct Source {
+    pub name: String,
+    /// Relative trust in this source's positions.
+    pub weight: f32,
+    pub packets: u64,
+    /// Packets another source had already delivered.
+    pub duplicates: u64,
+    /// Packets older than the drone's latest applied one.
+    pub late: u64,
+}
+
+/// How positions of one drone reported through several sources are combined.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum FusionPolicy {
+    /// Follow the highest-weight source heard recently; the others only take over
+    /// when it goes quiet.
+    #[default]
+    Best,
+    /// Weighted mean of the latest position through every source heard recently,
+    /// over the reports close in sender time to the newest one.
+    Weighted,
+}
+
+/// The latest report of a drone through one source.
+#[derive(Debug, Clone, Copy)]
+pub struct Feed {
+    pub source: SourceId,
+    pub pos: [f32; 3],
+    /// Sender time of the report.
+    pub ts_ms: u128,
+    pub at: Instant,
+}
+
+/// When and where a packet was received.
+#[derive(Debug, Clone, Copy)]
+pub struct Arrival {
+    /// Monotonic receive instant (synthetic during replay).
+    pub at: Instant,
+    /// Receive wall-clock time, ms since the Unix epoch.
+    pub wall_ms: u128,
+    pub source: SourceId,
+}
+
+impl Arrival {
+    pub fn now(source: SourceId) -> Self {
+        Self {
+            at: Instant::now(),
+            wall_ms: now_ms(),
+            source,
+        }
+    }
+}
+
+/// Where a drone is in its life as seen by the dashboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Lifecycle {
+    /// Heard from recently.
+    Active,
+    /// Silent for longer than [`LifecycleConfig::stale_after`].
+    Stale,
+    /// Silent for longer than [`LifecycleConfig::lost_after`]; kept as a ghost at its
+    /// last known position unless lost drones are removed.
+    Lost,
+    /// Reported one of the [`LANDED_STATUSES`].
+    Landed,
+}
+
+impl Lifecycle {
+    pub const ALL: [Lifecycle; 4] = [
+        Lifecycle::Active,
+        Lifecycle::Stale,
+        Lifecycle::Lost,
+        Lifecycle::Landed,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Lifecycle::Active => "active",
+            Lifecycle::Stale => "stale",
+            Lifecycle::Lost => "lost",
+            Lifecycle::Landed => "landed",
+        }
+    }
+}
+
+/// Timeouts and policy for [`Lifecycle`] transitions.
+#[derive(Debug, Clone, Copy)]
+pub struct LifecycleConfig {
+    pub stale_after: Duration,
+    pub lost_after: Duration,
+    /// Drop lost drones from the state instead of keeping them as ghosts.
+    pub remove_lost: bool,
+}
+
+impl Default for LifecycleConfig {
+    fn default() -> Self {
+        Self {
+            stale_after: Duration::from_secs(2),
+            lost_after: Duration::from_secs(10),
+            remove_lost: false,
+        }
+    }
+}
+
+/// A drone moved between lifecycle states.
+#[derive(Debug, Clone)]
+pub struct LifecycleEvent {
+    pub drone: u32,
+    /// `None` for a drone heard for the first time.
+    pub from: Option<Lifecycle>,
+    pub to: Lifecycle,
+    /// The drone was dropped from the state on this transition.
+    pub removed: bool,
+    pub at: Instant,
+}
+
+/// A geofence a drone is currently violating.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Breach {
+    pub fence: String,
+    pub kind: BreachKind,
+    pub since: Instant,
+}
+
+/// A drone started or stopped violating a geofence.
+#[derive(Debug, Clone)]
+pub struct FenceEvent {
+    pub drone: u32,
+    pub fence: String,
+    pub kind: BreachKind,
+    /// `false` when the breach began, `true` when it ended.
+    pub cleared: bool,
+    pub at: Instant,
+    pub ts_ms: u128,
+}
+
+/// One point of a drone's chart history.
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    pub at: Instant,
+    pub battery: f32,
+    pub z: f32,
+    pub speed: Option<f32>,
+    /// Packet rate over the link's rate window.
+    pub rate_hz: f32,
+}
+
+/// Bounded, progressively decimated time series of [`Sample`]s.
+#[derive(Debug, Clone)]
+pub struct History {
+    samples: VecDeque<Sample>,
+    period: Duration,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self {
+            samples: VecDeque::new(),
+            period: HISTORY_PERIOD,
+        }
+    }
+}
+
+impl History {
+    /// Oldest first.
+    pub fn samples(&self) -> &VecDeque<Sample> {
+        &self.samples
+    }
+
+    /// Current spacing between samples.
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    fn record(&mut self, sample: Sample) {
+        if let Some(last) = self.samples.back() {
+            if sample.at.saturating_duration_since(last.at) < self.period {
+                return;
+            }
+        }
+        self.samples.push_back(sample);
+        self.decimate();
+    }
+
+    /// Slot a late sample in at its time, unless a neighbour already covers it.
+    fn insert(&mut self, sample: Sample) {
+        let i = self.samples.partition_point(|s| s.at <= sample.at);
+        let near = |s: &Sample| s.at.max(sample.at) - s.at.min(sample.at) < self.period;
+        let before = i.checked_sub(1).and_then(|j| self.samples.get(j));
+        if before.is_some_and(near) || self.samples.get(i).is_some_and(near) {
+            return;
+        }
+        self.samples.insert(i, sample);
+        self.decimate();
+    }
+
+    fn decimate(&mut self) {
+        if self.samples.len() > HISTORY_MAX_SAMPLES {
+            self.samples = self.samples.iter().step_by(2).copied().collect();
+            self.period *= 2;
+        }
+    }
+}
+
+/// Where a silent drone probably is, extrapolated from its last known velocity.
+#[derive(Debug, Clone, Copy)]
+pub struct Prediction {
+    pub pos: [f32; 3],
+    /// 1-sigma horizontal position error.
+    pub sigma: f32,
+    /// Time extrapolated over, capped at [`TrackConfig::predict_horizon`].
+    pub ahead: Duration,
+}
+
+/// Packets in a row stamped behind a drone's latest one, see
+/// [`DroneState::clock_went_back`].
+#[derive(Debug, Clone, Copy)]
+struct BehindRun {
+    /// Arrival of the first.
+    since: Instant,
+    first_ts_ms: u128,
+    last_ts_ms: u128,
+    packets: u32,
+}
+
+/// Fused view of a single drone.
+#[derive(Debug, Clone)]
+pub struct DroneState {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub battery: f32,
+    pub status: String,
+    pub last_ts_ms: u128,
+    pub last_seen: Instant,
+
+    // Optional kinematics, as last reported (None if the sender omits them)
+    pub vel: Option<[f32; 3]>,
+    pub ground_speed: Option<f32>,
+    pub heading: Option<f32>,
+    pub pitch: Option<f32>,
+    pub roll: Option<f32>,
+    pub gps_fix: Option<GpsFix>,
+    pub satellites: Option<u8>,
+
+    pub link: LinkStats,
+
+    /// Source of the applied position, and the latest report through each source.
+    pub source: SourceId,
+    pub feeds: Vec<Feed>,
+    /// Sender time of the report the position was last applied from.
+    placed_ts_ms: u128,
+    /// Current run of packets stamped behind `last_ts_ms` with increasing times.
+    behind: Option<BehindRun>,
+    /// Recently received `ts_ms`, newest last, for de-duplication.
+    recent_ts: VecDeque<u128>,
+    /// Packets that arrived after a newer one had been applied; they only reach
+    /// the link stats and the chart history.
+    pub late: u64,
+
+    pub lifecycle: Lifecycle,
+
+    /// Geofences violated at the last update.
+    pub breaches: Vec<Breach>,
+
+    // Visual smoothing / trails
+    pub smoothed_x: f32,
+    pub smoothed_y: f32,
+    /// Kalman track, while that estimator is selected.
+    pub track: Option<Kalman>,
+    // (x, y, when recorded)
+    pub trail: VecDeque<(f32, f32, Instant)>,
+}
+
+impl DroneState {
+    /// Direction of travel in degrees (0 = north, clockwise): the reported heading,
+    /// else the reported or estimated velocity vector.
+    pub fn course_deg(&self) -> Option<f32> {
+        self.heading.or_else(|| {
+            let v = self.vel.or_else(|| self.track.as_ref().map(Kalman::velocity))?;
+            (v[0].hypot(v[1]) > 0.2).then(|| v[0].atan2(v[1]).to_degrees().rem_euclid(360.0))
+        })
+    }
+
+    /// Reported ground speed, else the speed of the reported velocity vector, else
+    /// the Kalman estimate.
+    pub fn speed(&self) -> Option<f32> {
+        self.ground_speed
+            .or_else(|| self.vel.map(|v| v[0].hypot(v[1])))
+            .or_else(|| self.track.as_ref().map(Kalman::speed))
+    }
+
+    /// Dead-reckoned position at `now` once the drone has been silent for
+    /// [`TrackConfig::predict_after`]: from the Kalman track if there is one, else
+    /// from the smoothed position and reported velocity. Landed drones stay put.
+    pub fn predict(&self, now: Instant, tracking: &TrackConfig) -> Option<Prediction> {
+        let silent = now.saturating_duration_since(self.last_seen);
+        if silent < tracking.predict_after
+            || tracking.predict_horizon.is_zero()
+            || self.lifecycle == Lifecycle::Landed
+        {
+            return None;
+        }
+        let ahead = silent.min(tracking.predict_horizon);
+        let dt = ahead.as_secs_f32();
+        if let Some(k) = &self.track {
+            // The track may lag the last packet if that one was gated out
+            let lag = self.last_ts_ms.saturating_sub(k.ts_ms()) as f32 / 1000.0;
+            let (pos, var) = k.predict(lag + dt, tracking);
+            return Some(Prediction {
+                pos,
+                sigma: var[0].max(var[1]).sqrt(),
+                ahead,
+            });
+        }
+        let v = self.vel?;
+        let q = tracking.accel_noise.powi(2);
+        Some(Prediction {
+            pos: [
+                self.smoothed_x + v[0] * dt,
+                self.smoothed_y + v[1] * dt,
+                self.z + v[2] * dt,
+            ],
+            sigma: (tracking.position_noise.powi(2) + q * dt.powi(3) / 3.0).sqrt(),
+            ahead,
+        })
+    }
+
+    /// Feeds heard from within `window` of `now`.
+    pub fn fresh_feeds(&self, now: Instant, window: Duration) -> impl Iterator<Item = &Feed> {
+        self.feeds
+            .iter()
+            .filter(move |f| now.saturating_duration_since(f.at) <= window)
+    }
+
+    /// Whether a packet with this timestamp was already received.
+    pub fn seen(&self, ts_ms: u128) -> bool {
+        self.recent_ts.contains(&ts_ms)
+    }
+
+    fn remember(&mut self, ts_ms: u128) {
+        self.recent_ts.push_back(ts_ms);
+        if self.recent_ts.len() > DEDUP_WINDOW {
+            self.recent_ts.pop_front();
+        }
+    }
+
+    /// Account for a packet older than the latest applied one: it counts towards
+    /// link stats and de-duplication but leaves the live state alone.
+    fn update_late(&mut self, t: &Telemetry, arrival: Arrival) {
+        self.link.on_packet(t.seq, t.ts_ms, arrival.wall_ms, arrival.at);
+        self.remember(t.ts_ms);
+        self.late += 1;
+    }
+
+    /// Whether a packet stamped `ts_ms` means the sender's clock went back (a
+    /// reboot, a restarted simulator, a looping replay) rather than that it was
+    /// delayed: the jump back is longer than any plausible delay, or the sender
+    /// has kept counting on from behind the latest packet for a while (see
+    /// [`CLOCK_RESET_PACKETS`]).
+    fn clock_went_back(&mut self, ts_ms: u128, at: Instant, lifecycle: &LifecycleConfig) -> bool {
+        if ts_ms >= self.last_ts_ms {
+            self.behind = None;
+            return false;
+        }
+        if self.last_ts_ms - ts_ms > lifecycle.lost_after.as_millis() {
+            return true;
+        }
+        // Copies through other sources neither extend nor break the run
+        let run = match self.behind {
+            Some(run) if ts_ms >= run.last_ts_ms => BehindRun {
+                last_ts_ms: ts_ms,
+                packets: run.packets + (ts_ms > run.last_ts_ms) as u32,
+                ..run
+            },
+            _ => BehindRun {
+                since: at,
+                first_ts_ms: ts_ms,
+                last_ts_ms: ts_ms,
+                packets: 1,
+            },
+        };
+        self.behind = Some(run);
+        run.packets >= CLOCK_RESET_PACKETS
+            && at.saturating_duration_since(run.since) >= lifecycle.stale_after
+            && run.last_ts_ms - run.first_ts_ms >= lifecycle.stale_after.as_millis()
+    }
+
+    /// Forget sender times from before a clock reset so the drone follows the
+    /// new clock from the packet stamped `ts_ms`.
+    fn restart_clock(&mut self, ts_ms: u128) {
+        self.recent_ts.clear();
+        self.feeds.clear();
+        self.track = None;
+        self.last_ts_ms = ts_ms;
+        self.placed_ts_ms = ts_ms;
+        self.behind = None;
+    }
+
+    fn record_feed(&mut self, source: SourceId, t: &Telemetry, at: Instant) {
+        let feed = Feed {
+            source,
+            pos: [t.x, t.y, t.z],
+            ts_ms: t.ts_ms,
+            at,
+        };
+        match self.feeds.iter_mut().find(|f| f.source == source) {
+            Some(f) => *f = feed,
+            None => self.feeds.push(feed),
+        }
+    }
+
+    pub fn new(t: &Telemetry, now: Instant) -> Self {
+        Self {
+            x: t.x,
+            y: t.y,
+            z: t.z,
+            battery: t.battery,
+            status: t.status.clone(),
+            last_ts_ms: t.ts_ms,
+            last_seen: now,
+            vel: t.vel,
+            ground_speed: t.ground_speed,
+            heading: t.heading,
+            pitch: t.pitch,
+            roll: t.roll,
+            gps_fix: t.gps_fix,
+            satellites: t.satellites,
+            link: LinkStats::default(),
+            source: 0,
+            feeds: Vec::new(),
+            placed_ts_ms: t.ts_ms,
+            behind: None,
+            recent_ts: VecDeque::with_capacity(DEDUP_WINDOW),
+            late: 0,
+            lifecycle: Lifecycle::Active,
+            breaches: Vec::new(),
+            smoothed_x: t.x,
+            smoothed_y: t.y,
+            track: None,
+            trail: VecDeque::with_capacity(128),
+        }
+    }
+
+    /// Fold one packet into this drone: link stats and raw values, then the
+    /// position `fused` from it, if any, with smoothing and trail. `None` leaves
+    /// the position to the source leading the drone.
+    pub fn update(
+        &mut self,
+        t: Telemetry,
+        fused: Option<[f32; 3]>,
+        arrival: Arrival,
+        tracking: &TrackConfig,
+    ) {
+        self.link.on_packet(t.seq, t.ts_ms, arrival.wall_ms, arrival.at);
+        self.remember(t.ts_ms);
+        if let Some(pos) = fused {
+            self.place(&t, pos, arrival, tracking);
+        }
+        self.battery = t.battery;
+        self.status = t.status;
+        self.last_ts_ms = t.ts_ms;
+        self.last_seen = arrival.at;
+    }
+
+    /// Move the drone to `pos` with the kinematics reported in `t`.
+    fn place(&mut self, t: &Telemetry, pos: [f32; 3], arrival: Arrival, tracking: &TrackConfig) {
+        let now = arrival.at;
+        let silent = self
+            .trail
+            .back()
+            .map_or(Duration::MAX, |&(_, _, at)| now.saturating_duration_since(at));
+        self.source = arrival.source;
+        self.placed_ts_ms = t.ts_ms;
+
+        [self.x, self.y, self.z] = pos;
+        self.vel = t.vel;
+        self.ground_speed = t.ground_speed;
+        self.heading = t.heading;
+        self.pitch = t.pitch;
+        self.roll = t.roll;
+        self.gps_fix = t.gps_fix;
+        self.satellites = t.satellites;
+
+        match tracking.estimator {
+            // After a gap, snap to the report instead of crawling over from where
+            // the drone was last heard
+            Estimator::Ema if silent >= tracking.predict_after => {
+                self.track = None;
+                (self.smoothed_x, self.smoothed_y) = (self.x, self.y);
+            }
+            Estimator::Ema => {
+                self.track = None;
+                self.smoothed_x += EMA_ALPHA * (self.x - self.smoothed_x);
+                self.smoothed_y += EMA_ALPHA * (self.y - self.smoothed_y);
+            }
+            Estimator::Kalman => {
+                let track = match &mut self.track {
+                    Some(k) => {
+                        k.step(pos, self.vel, t.ts_ms, tracking);
+                        k
+                    }
+                    None => self.track.insert(Kalman::new(pos, self.vel, t.ts_ms, tracking)),
+                };
+                [self.smoothed_x, self.smoothed_y, _] = track.position();
+            }
+        }
+
+        // Record trail using smoothed coords
+        self.trail.push_back((self.smoothed_x, self.smoothed_y, now));
+
+        while self.trail.len() > TRAIL_MAX_POINTS {
+            self.trail.pop_front();
+        }
+        while let Some(&(_, _, when)) = self.trail.front() {
+            if now.saturating_duration_since(when) > TRAIL_MAX_AGE {
+                self.trail.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+}
+
+/// Everything the dashboard knows about the fleet.
+#[derive(Default)]
+pub struct AppState {
+    pub drones: HashMap<u32, DroneState>,
+    pub total_packets: u64,
+    pub last_packet_at: Option<Instant>,
+
+    /// Frozen "now" while replaying a recording; `None` means live wall-clock time.
+    pub clock: Option<Instant>,
+
+    /// Geofences checked on every update.
+    pub fences: Vec<Geofence>,
+    /// Breach start/end history, oldest first, capped at [`FENCE_EVENTS_MAX`].
+    pub fence_events: VecDeque<FenceEvent>,
+
+    /// Home point of the local map frame. Geodetic packets are projected into it;
+    /// the first one fixes it if none was configured.
+    pub home: Option<LocalFrame>,
+
+    /// Receivers packets arrive through, indexed by [`Arrival::source`], and how
+    /// their reports of the same drone are combined.
+    pub sources: Vec<Source>,
+    pub policy: FusionPolicy,
+
+    /// Alert rules and the alerts they raised.
+    pub alerts: AlertEngine,
+
+    /// Lifecycle timeouts, and the transitions they caused, oldest first, capped at
+    /// [`LIFECYCLE_EVENTS_MAX`].
+    pub lifecycle: LifecycleConfig,
+    pub lifecycle_events: VecDeque<LifecycleEvent>,
+
+    /// Chart history per drone; kept out of [`DroneState`] so per-frame snapshots
+    /// of the fleet stay cheap.
+    pub history: HashMap<u32, History>,
+
+    /// Position estimator and its tuning.
+    pub tracking: TrackConfig,
+
+    /// Datagrams that failed to decode or validate.
+    pub diagnostics: Diagnostics,
+}
+
+impl AppState {
+    /// Reference instant for ages (`last_seen`, trail points) shown in the UI.
+    pub fn now(&self) -> Instant {
+        self.clock.unwrap_or_else(Instant::now)
+    }
+
+    /// Drop everything learned from packets, keeping configuration (home point,
+    /// fences, alert rules, lifecycle timeouts, fusion and estimator settings).
+    pub fn reset(&mut self) {
+        *self = AppState {
+            home: self.home,
+            fences: std::mem::take(&mut self.fences),
+            alerts: AlertEngine::new(std::mem::take(&mut self.alerts.rules)),
+            lifecycle: self.lifecycle,
+            sources: std::mem::take(&mut self.sources)
+                .into_iter()
+                .map(|s| Source {
+                    packets: 0,
+                    duplicates: 0,
+                    late: 0,
+                    ..s
+                })
+                .collect(),
+            policy: self.policy,
+            tracking: self.tracking,
+            ..Default::default()
+        };
+    }
+
+    /// Register a receiver, returning the id to tag its packets with.
+    pub fn add_source(&mut self, name: impl Into<String>, weight: f32) -> SourceId {
+        self.sources.push(Source {
+            name: name.into(),
+            weight,
+            packets: 0,
+            duplicates: 0,
+            late: 0,
+        });
+        self.sources.len() - 1
+    }
+
+    /// Id of the source called `name`, registering it with weight 1 if it's new.
+    pub fn source_named(&mut self, name: &str) -> SourceId {
+        match self.sources.iter().position(|s| s.name == name) {
+            Some(id) => id,
+            None => self.add_source(name, 1.0),
+        }
+    }
+
+    fn weight(&self, source: SourceId) -> f32 {
+        self.sources.get(source).map_or(1.0, |s| s.weight.max(0.0))
+    }
+
+    /// Position to apply for a packet through `source`, reported at `pos`: the
+    /// packet's own under [`FusionPolicy::Best`] unless a better source is feeding
+    /// the drone (then `None`), the weighted mean of fresh feeds within
+    /// [`WEIGHTED_SPAN_MS`] of the newest under [`FusionPolicy::Weighted`].
+    fn fuse(
+        &self,
+        d: &DroneState,
+        source: SourceId,
+        pos: [f32; 3],
+        now: Instant,
+    ) -> Option<[f32; 3]> {
+        let window = self.lifecycle.stale_after;
+        match self.policy {
+            FusionPolicy::Best => {
+                // Ties keep the current source so equal receivers don't flap
+                let best = d
+                    .fresh_feeds(now, window)
+                    .map(|f| f.source)
+                    .max_by(|&a, &b| {
+                        self.weight(a)
+                            .total_cmp(&self.weight(b))
+                            .then((a == d.source).cmp(&(b == d.source)))
+                    })
+                    .unwrap_or(source);
+                (best == source).then_some(pos)
+            }
+            FusionPolicy::Weighted => {
+                let newest = d.fresh_feeds(now, window).map(|f| f.ts_ms).max();
+                let mut sum = [0.0f32; 3];
+                let mut total = 0.0;
+                for f in d
+                    .fresh_feeds(now, window)
+                    .filter(|f| newest.is_some_and(|n| n - f.ts_ms <= WEIGHTED_SPAN_MS))
+                {
+                    let w = self.weight(f.source);
+                    for (s, p) in sum.iter_mut().zip(f.pos) {
+                        *s += w * p;
+                    }
+                    total += w;
+                }
+                Some(if total > 0.0 {
+                    sum.map(|s| s / total)
+                } else {
+                    pos
+                })
+            }
+        }
+    }
+
+    /// Number of drones in each lifecycle state.
+    pub fn lifecycle_counts(&self) -> [(Lifecycle, usize); 4] {
+        Lifecycle::ALL.map(|l| (l, self.drones.values().filter(|d| d.lifecycle == l).count()))
+    }
+
+    fn classify(&self, d: &DroneState, now: Instant) -> Lifecycle {
+        if LANDED_STATUSES.iter().any(|s| d.status.eq_ignore_ascii_case(s)) {
+            return Lifecycle::Landed;
+        }
+        let age = now.saturating_duration_since(d.last_seen);
+        if age >= self.lifecycle.lost_after {
+            Lifecycle::Lost
+        } else if age >= self.lifecycle.stale_after {
+            Lifecycle::Stale
+        } else {
+            Lifecycle::Active
+        }
+    }
+
+    fn log_lifecycle(&mut self, event: LifecycleEvent) {
+        self.lifecycle_events.push_back(event);
+        while self.lifecycle_events.len() > LIFECYCLE_EVENTS_MAX {
+            self.lifecycle_events.pop_front();
+        }
+    }
+
+    /// Reclassify a drone that just sent a packet, logging its arrival or transition.
+    fn refresh_lifecycle(&mut self, id: u32, is_new: bool, at: Instant) {
+        let Some(d) = self.drones.get(&id) else {
+            return;
+        };
+        let (from, to) = (d.lifecycle, self.classify(d, at));
+        if is_new || from != to {
+            if let Some(d) = self.drones.get_mut(&id) {
+                d.lifecycle = to;
+            }
+            self.log_lifecycle(LifecycleEvent {
+                drone: id,
+                from: (!is_new).then_some(from),
+                to,
+                removed: false,
+                at,
+            });
+        }
+    }
+
+    /// Age every drone through the lifecycle states, logging transitions and
+    /// removing lost drones if configured. Call periodically: silence alone
+    /// triggers transitions.
+    pub fn update_lifecycles(&mut self) {
+        let now = self.now();
+        let changes: Vec<(u32, Lifecycle, Lifecycle)> = self
+            .drones
+            .iter()
+            .map(|(id, d)| (*id, d.lifecycle, self.classify(d, now)))
+            .filter(|(_, from, to)| from != to)
+            .collect();
+        for (id, from, to) in changes {
+            let removed = to == Lifecycle::Lost && self.lifecycle.remove_lost;
+            if removed {
+                self.forget(id);
+            } else if let Some(d) = self.drones.get_mut(&id) {
+                d.lifecycle = to;
+            }
+            self.log_lifecycle(LifecycleEvent {
+                drone: id,
+                from: Some(from),
+                to,
+                removed,
+                at: now,
+            });
+        }
+    }
+
+    /// Evaluate the alert rules against the current fleet. `now_ms` is the wall
+    /// time matching [`AppState::now`], stamped on the alerts.
+    pub fn check_alerts(&mut self, now_ms: u128) -> Raised {
+        let now = self.now();
+        let mut alerts = std::mem::take(&mut self.alerts);
+        let raised = alerts.evaluate(self, now, now_ms);
+        self.alerts = alerts;
+        raised
+    }
+
+    /// Drop a drone and its history; it reappears with its next packet.
+    pub fn forget(&mut self, id: u32) {
+        self.drones.remove(&id);
+        self.history.remove(&id);
+    }
+
+    /// Replace the geofences and re-check every drone against them.
+    pub fn set_fences(&mut self, fences: Vec<Geofence>) {
+        self.fences = fences;
+        let ids: Vec<u32> = self.drones.keys().copied().collect();
+        for id in ids {
+            self.check_fences(id);
+        }
+    }
+
+    pub fn add_fence(&mut self, fence: Geofence) {
+        let mut fences = std::mem::take(&mut self.fences);
+        fences.push(fence);
+        self.set_fences(fences);
+    }
+
+    /// Recompute a drone's breaches, logging the ones that started or ended.
+    fn check_fences(&mut self, id: u32) {
+        let Some(d) = self.drones.get_mut(&id) else {
+            return;
+        };
+        let now = d.last_seen;
+        let mut current: Vec<Breach> = Vec::new();
+        for f in &self.fences {
+            if let Some(kind) = f.check(d.x, d.y, d.z) {
+                // Keep the original start time of an ongoing breach
+                let since = d
+                    .breaches
+                    .iter()
+                    .find(|b| b.fence == f.name && b.kind == kind)
+                    .map_or(now, |b| b.since);
+                current.push(Breach {
+                    fence: f.name.clone(),
+                    kind,
+                    since,
+                });
+            }
+        }
+
+        let same = |a: &Breach, b: &Breach| a.fence == b.fence && a.kind == b.kind;
+        let ended = d.breaches.iter().filter(|b| !current.iter().any(|c| same(b, c)));
+        let started = current.iter().filter(|c| !d.breaches.iter().any(|b| same(b, c)));
+        let events: Vec<FenceEvent> = ended
+            .map(|b| (b, true))
+            .chain(started.map(|b| (b, false)))
+            .map(|(b, cleared)| FenceEvent {
+                drone: id,
+                fence: b.fence.clone(),
+                kind: b.kind,
+                cleared,
+                at: now,
+                ts_ms: d.last_ts_ms,
+            })
+            .collect();
+        d.breaches = current;
+
+        self.fence_events.extend(events);
+        while self.fence_events.len() > FENCE_EVENTS_MAX {
+            self.fence_events.pop_front();
+        }
+    }
+
+    /// Replace x/y/z of a geodetic packet with east/north/up metres from home.
+    fn project(&mut self, t: &mut Telemetry) {
+        let (Some(lat), Some(lon)) = (t.lat, t.lon) else {
+            return;
+        };
+        let home = *self.home.get_or_insert_with(|| {
+            LocalFrame::new(Geodetic::new(lat, lon, t.alt.unwrap_or(0.0) as f64))
+        });
+        // Without an altitude, stay on the home level and keep the reported z
+        let alt = t.alt.map_or(home.origin().alt, |a| a as f64);
+        let [e, n, u] = home.to_enu(&Geodetic::new(lat, lon, alt));
+        t.x = e as f32;
+        t.y = n as f32;
+        if t.alt.is_some() {
+            t.z = u as f32;
+        }
+    }
+
+    /// Apply one decoded packet: drop it if another source already delivered it,
+    /// keep it out of the live state if a newer packet was already applied (it
+    /// still lands in the chart history at its time), otherwise fold it in, with
+    /// the position the fusion policy picks from the drone's sources. A sender
+    /// whose clock went back starts over from its new clock.
+    pub fn apply(&mut self, mut t: Telemetry, arrival: Arrival) {
+        self.project(&mut t);
+        self.total_packets += 1;
+        self.last_packet_at = Some(arrival.at);
+        if let Some(s) = self.sources.get_mut(arrival.source) {
+            s.packets += 1;
+        }
+
+        let id = t.id;
+        let is_new = !self.drones.contains_key(&id);
+        let entry = self
+            .drones
+            .entry(id)
+            .or_insert_with(|| DroneState::new(&t, arrival.at));
+        if entry.clock_went_back(t.ts_ms, arrival.at, &self.lifecycle) {
+            entry.restart_clock(t.ts_ms);
+        }
+        if entry.seen(t.ts_ms) {
+            entry.record_feed(arrival.source, &t, arrival.at);
+            if let Some(s) = self.sources.get_mut(arrival.source) {
+                s.duplicates += 1;
+            }
+            // The leading source's copy of the newest packet still moves the drone
+            // if a lesser source happened to deliver it first
+            let d = &self.drones[&id];
+            if t.ts_ms == d.last_ts_ms && d.placed_ts_ms < t.ts_ms {
+                let reported = [t.x, t.y, t.z];
+                if let Some(pos) = self.fuse(d, arrival.source, reported, arrival.at) {
+                    let entry = self.drones.get_mut(&id).expect("inserted above");
+                    entry.place(&t, pos, arrival, &self.tracking);
+                    if !self.fences.is_empty() {
+                        self.check_fences(id);
+                    }
+                }
+            }
+            return;
+        }
+        if !is_new && t.ts_ms < entry.last_ts_ms {
+            entry.update_late(&t, arrival);
+            // Place it by sender time relative to the latest applied packet
+            let behind = Duration::from_millis((entry.last_ts_ms - t.ts_ms) as u64);
+            let sample = entry.last_seen.checked_sub(behind).map(|at| Sample {
+                at,
+                battery: t.battery,
+                z: t.z,
+                speed: t.ground_speed.or_else(|| t.vel.map(|v| v[0].hypot(v[1]))),
+                rate_hz: entry.link.rate_hz(arrival.at),
+            });
+            if let Some(s) = self.sources.get_mut(arrival.source) {
+                s.late += 1;
+            }
+            if let Some(sample) = sample {
+                self.history.entry(id).or_default().insert(sample);
+            }
+            return;
+        }
+        entry.record_feed(arrival.source, &t, arrival.at);
+
+        let reported = [t.x, t.y, t.z];
+        let fused = self.fuse(&self.drones[&id], arrival.source, reported, arrival.at);
+        let entry = self.drones.get_mut(&id).expect("inserted above");
+        entry.update(t, fused, arrival, &self.tracking);
+        self.history.entry(id).or_default().record(Sample {
+            at: arrival.at,
+            battery: entry.battery,
+            z: entry.z,
+            speed: entry.speed(),
+            rate_hz: entry.link.rate_hz(arrival.at),
+        });
+        if !self.fences.is_empty() {
+            self.check_fences(id);
+        }
+        self.refresh_lifecycle(id, is_new, arrival.at);
+    }
+
+    /// Bounding box of drones that aren't lost, and their trails.
+    pub fn bounds(&self) -> Option<Bounds> {
+        let now = self.now();
+        let mut out: Option<Bounds> = None;
+        let mut add = |x: f32, y: f32| match out.as_mut() {
+            Some(b) => b.include(x, y),
+            None => out = Some(Bounds::point(x, y)),
+        };
+        for d in self.drones.values() {
+            if self.classify(d, now) == Lifecycle::Lost {
+                continue;
+            }
+            add(d.smoothed_x, d.smoothed_y);
+            for &(x, y, _) in &d.trail {
+                add(x, y);
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet(ts_ms: u128, x: f32, battery: f32) -> Telemetry {
+        Telemetry {
+            id: 1,
+            x,
+            battery,
+            status: "OK".into(),
+            ts_ms,
+            ..Default::default()
+        }
+    }
+
+    fn arrival(at: Instant, source: SourceId) -> Arrival {
+        Arrival {
+            at,
+            wall_ms: 0,
+            source,
+        }
+    }
+
+    /// Two sources, the first weighted higher.
+    fn state(policy: FusionPolicy, weights: [f32; 2]) -> (AppState, SourceId, SourceId) {
+        let mut state = AppState {
+            policy,
+            ..Default::default()
+        };
+        let a = state.add_source("a", weights[0]);
+        let b = state.add_source("b", weights[1]);
+        (state, a, b)
+    }
+
+    #[test]
+    fn best_follows_the_heaviest_fresh_source() {
+        let (mut state, a, b) = state(FusionPolicy::Best, [2.0, 1.0]);
+        let t0 = Instant::now();
+        let ms = |n: u64| t0 + Duration::from_millis(n);
+
+        state.apply(packet(1000, 0.0, 90.0), arrival(ms(0), a));
+        // A lesser source only refreshes the rest of the state
+        state.apply(packet(1100, 10.0, 80.0), arrival(ms(100), b));
+        let d = &state.drones[&1];
+        assert_eq!((d.x, d.source), (0.0, a));
+        assert_eq!((d.battery, d.last_ts_ms), (80.0, 1100));
+        assert_eq!(d.link.received, 2);
+        assert!(d.seen(1100));
+
+        state.apply(packet(1200, 1.0, 79.0), arrival(ms(200), a));
+        assert_eq!(state.drones[&1].x, 1.0);
+
+        // The leader went quiet: the other source takes over
+        state.apply(packet(4000, 20.0, 70.0), arrival(ms(3000), b));
+        let d = &state.drones[&1];
+        assert_eq!((d.x, d.source), (20.0, b));
+    }
+
+    #[test]
+    fn weighted_averages_feeds_close_in_sender_time() {
+        let (mut state, a, b) = state(FusionPolicy::Weighted, [3.0, 1.0]);
+        let t0 = Instant::now();
+        let ms = |n: u64| t0 + Duration::from_millis(n);
+
+        state.apply(packet(1000, 0.0, 90.0), arrival(ms(0), a));
+        state.apply(packet(1050, 4.0, 90.0), arrival(ms(50), b));
+        assert_eq!(state.drones[&1].x, 1.0);
+
+        // A's report is now too old to average with, though still fresh
+        state.apply(packet(1500, 8.0, 90.0), arrival(ms(500), b));
+        assert_eq!(state.drones[&1].x, 8.0);
+    }
+
+    #[test]
+    fn duplicates_are_counted_once() {
+        let (mut state, a, b) = state(FusionPolicy::Best, [2.0, 1.0]);
+        let t0 = Instant::now();
+        let ms = |n: u64| t0 + Duration::from_millis(n);
+
+        state.apply(packet(900, 0.0, 90.0), arrival(ms(0), a));
+        // B relays packet 1000 first; the leader's copy then moves the drone
+        state.apply(packet(1000, 5.0, 89.0), arrival(ms(100), b));
+        assert_eq!(state.drones[&1].x, 0.0);
+        state.apply(packet(1000, 5.0, 89.0), arrival(ms(110), a));
+        let d = &state.drones[&1];
+        assert_eq!((d.x, d.source), (5.0, a));
+        assert_eq!(d.link.received, 2);
+        assert_eq!(state.sources[a].duplicates, 1);
+
+        // Another copy changes nothing
+        state.apply(packet(1000, 5.0, 89.0), arrival(ms(120), b));
+        let d = &state.drones[&1];
+        assert_eq!((d.x, d.source, d.link.received), (5.0, a, 2));
+        assert_eq!(state.sources[b].duplicates, 1);
+        assert_eq!(state.sources[b].packets, 2);
+        assert_eq!(state.total_packets, 4);
+    }
+
+    #[test]
+    fn late_packet_goes_into_history_only() {
+        let (mut state, a, _) = state(FusionPolicy::Best, [1.0, 1.0]);
+        let t0 = Instant::now();
+        let ms = |n: u64| t0 + Duration::from_millis(n);
+
+        state.apply(packet(1000, 0.0, 90.0), arrival(ms(0), a));
+        state.apply(packet(2000, 2.0, 80.0), arrival(ms(1000), a));
+        state.apply(packet(1500, 1.0, 85.0), arrival(ms(1100), a));
+
+        let d = &state.drones[&1];
+        assert_eq!((d.x, d.battery, d.last_ts_ms), (2.0, 80.0, 2000));
+        assert_eq!((d.late, state.sources[a].late), (1, 1));
+        assert_eq!(d.link.received, 3);
+        assert!(d.seen(1500));
+        // Slotted in by sender time, between the two applied packets
+        let samples = state.history[&1].samples();
+        assert_eq!(samples.len(), 3);
+        assert_eq!((samples[1].at, samples[1].battery), (ms(500), 85.0));
+    }
+
+    #[test]
+    fn clock_reset_recovers() {
+        let (mut state, a, _) = state(FusionPolicy::Best, [1.0, 1.0]);
+        let t0 = Instant::now();
+        let ms = |n: u64| t0 + Duration::from_millis(n);
+
+        // A simulator restarted with a slightly earlier start: the new clock is
+        // followed once it has kept counting from behind for the stale timeout
+        for i in 0..10 {
+            state.apply(packet(10_000 + i * 100, 0.0, 90.0), arrival(ms(i as u64 * 100), a));
+        }
+        for i in 0..20 {
+            let t = packet(5_000 + i * 100, 1.0, 90.0);
+            state.apply(t, arrival(ms(1000 + i as u64 * 100), a));
+        }
+        let d = &state.drones[&1];
+        assert_eq!((d.x, d.last_ts_ms, d.late), (0.0, 10_900, 20));
+        state.apply(packet(7_000, 7.0, 90.0), arrival(ms(3000), a));
+        let d = &state.drones[&1];
+        assert_eq!((d.x, d.last_ts_ms), (7.0, 7_000));
+        state.apply(packet(7_100, 8.0, 90.0), arrival(ms(3100), a));
+        let d = &state.drones[&1];
+        assert_eq!((d.x, d.last_ts_ms, d.late), (8.0, 7_100, 20));
+        assert_eq!(d.track.as_ref().map(Kalman::ts_ms), Some(7_100));
+
+        // A reboot without a pause: the jump back is too long to be a delay
+        state.apply(packet(60_000, 9.0, 90.0), arrival(ms(3200), a));
+        state.apply(packet(500, 10.0, 90.0), arrival(ms(3300), a));
+        let d = &state.drones[&1];
+        assert_eq!((d.x, d.last_ts_ms, d.late), (10.0, 500, 20));
+    }
+
+    #[test]
+    fn delayed_packets_after_a_gap_are_late_not_a_reset() {
+        let (mut state, a, _) = state(FusionPolicy::Best, [1.0, 1.0]);
+        let t0 = Instant::now();
+        let ms = |n: u64| t0 + Duration::from_millis(n);
+        state.apply(packet(1000, 0.0, 90.0), arrival(ms(0), a));
+        state.apply(packet(2000, 1.0, 90.0), arrival(ms(1000), a));
+
+        // A single straggler after the link went quiet
+        state.apply(packet(1500, 9.0, 90.0), arrival(ms(4000), a));
+        let d = &state.drones[&1];
+        assert_eq!((d.x, d.last_ts_ms, d.late), (1.0, 2000, 1));
+        assert_eq!(d.track.as_ref().map(Kalman::ts_ms), Some(2000));
+
+        // Then a buffered burst, in and out of order, and the live stream again
+        for (i, ts) in [1600, 1800, 1700, 1900].into_iter().enumerate() {
+            state.apply(packet(ts, 9.0, 90.0), arrival(ms(6000 + i as u64), a));
+        }
+        state.apply(packet(8000, 2.0, 90.0), arrival(ms(7000), a));
+        let d = &state.drones[&1];
+        assert_eq!((d.x, d.last_ts_ms, d.late), (2.0, 8000, 5));
+        assert!(d.seen(2000) && d.seen(1500));
+    }
+
+    #[test]
+    fn fence_breach_logs_start_and_end_once() {
+        let (mut state, a, _) = state(FusionPolicy::Best, [1.0, 1.0]);
+        state.set_fences(vec![Geofence {
+            name: "Field".into(),
+            kind: crate::geofence::FenceKind::Inclusion,
+            shape: crate::geofence::FenceShape::Circle {
+                center: [0.0, 0.0],
+                radius: 10.0,
+            },
+            floor: None,
+            ceiling: None,
+        }]);
+        let t0 = Instant::now();
+        let ms = |n: u64| t0 + Duration::from_millis(n);
+
+        for (i, x) in [0.0, 5.0, 20.0, 25.0, 30.0, 5.0, 0.0].into_iter().enumerate() {
+            let i = i as u64;
+            state.apply(packet(1000 + i as u128 * 100, x, 90.0), arrival(ms(i * 100), a));
+        }
+        let events: Vec<(bool, u128)> =
+            state.fence_events.iter().map(|e| (e.cleared, e.ts_ms)).collect();
+        assert_eq!(events, [(false, 1200), (true, 1500)]);
+        assert!(state.fence_events.iter().all(|e| e.kind == BreachKind::Outside));
+        assert!(state.drones[&1].breaches.is_empty());
+    }
+
+    /// (from, to, removed) of every logged lifecycle transition.
+    fn transitions(state: &AppState) -> Vec<(Option<Lifecycle>, Lifecycle, bool)> {
+        state.lifecycle_events.iter().map(|e| (e.from, e.to, e.removed)).collect()
+    }
+
+    #[test]
+    fn silence_ages_drones_through_stale_to_lost() {
+        let (mut state, a, _) = state(FusionPolicy::Best, [1.0, 1.0]);
+        let t0 = Instant::now();
+        let secs = |n: u64| t0 + Duration::from_secs(n);
+        state.apply(packet(1000, 0.0, 90.0), arrival(t0, a));
+
+        for (at, expected) in [
+            (1, Lifecycle::Active),
+            (2, Lifecycle::Stale),
+            (9, Lifecycle::Stale),
+            (10, Lifecycle::Lost),
+            (60, Lifecycle::Lost),
+        ] {
+            state.clock = Some(secs(at));
+            state.update_lifecycles();
+            assert_eq!(state.drones[&1].lifecycle, expected, "after {at} s");
+        }
+        use Lifecycle::*;
+        assert_eq!(
+            transitions(&state),
+            [(None, Active, false), (Some(Active), Stale, false), (Some(Stale), Lost, false)]
+        );
+        assert_eq!(state.lifecycle_counts()[2], (Lost, 1));
+    }
+
+    #[test]
+    fn landed_drones_do_not_go_stale() {
+        let (mut state, a, _) = state(FusionPolicy::Best, [1.0, 1.0]);
+        let t0 = Instant::now();
+        let mut t = packet(1000, 0.0, 90.0);
+        t.status = "landed".into();
+        state.apply(t, arrival(t0, a));
+        assert_eq!(state.drones[&1].lifecycle, Lifecycle::Landed);
+
+        state.clock = Some(t0 + Duration::from_secs(60));
+        state.update_lifecycles();
+        assert_eq!(state.drones[&1].lifecycle, Lifecycle::Landed);
+        assert_eq!(transitions(&state), [(None, Lifecycle::Landed, false)]);
+
+        // Taking off again
+        state.apply(packet(61_000, 0.0, 90.0), arrival(t0 + Duration::from_secs(61), a));
+        assert_eq!(state.drones[&1].lifecycle, Lifecycle::Active);
+        let mut t = packet(62_000, 0.0, 90.0);
+        t.status = "RETIRED".into();
+        state.apply(t, arrival(t0 + Duration::from_secs(62), a));
+        assert_eq!(state.drones[&1].lifecycle, Lifecycle::Landed);
+        assert_eq!(state.lifecycle_events.len(), 3);
+    }
+
+    #[test]
+    fn lost_drones_can_be_removed() {
+        let (mut state, a, _) = state(FusionPolicy::Best, [1.0, 1.0]);
+        state.lifecycle.remove_lost = true;
+        let t0 = Instant::now();
+        state.apply(packet(1000, 0.0, 90.0), arrival(t0, a));
+        assert!(state.history.contains_key(&1));
+
+        state.clock = Some(t0 + Duration::from_secs(10));
+        state.update_lifecycles();
+        assert!(!state.drones.contains_key(&1));
+        assert!(!state.history.contains_key(&1));
+        let last = state.lifecycle_events.back().unwrap();
+        assert_eq!((last.drone, last.from, last.to), (1, Some(Lifecycle::Active), Lifecycle::Lost));
+        assert!(last.removed);
+    }
+
+    #[test]
+    fn lost_drone_comes_back_when_packets_resume() {
+        let (mut state, a, _) = state(FusionPolicy::Best, [1.0, 1.0]);
+        let t0 = Instant::now();
+        state.apply(packet(1000, 0.0, 90.0), arrival(t0, a));
+        state.clock = Some(t0 + Duration::from_secs(15));
+        state.update_lifecycles();
+        assert_eq!(state.drones[&1].lifecycle, Lifecycle::Lost);
+
+        state.apply(packet(16_000, 3.0, 85.0), arrival(t0 + Duration::from_secs(16), a));
+        let d = &state.drones[&1];
+        assert_eq!((d.lifecycle, d.x), (Lifecycle::Active, 3.0));
+        let last = state.lifecycle_events.back().unwrap();
+        assert_eq!((last.from, last.to), (Some(Lifecycle::Lost), Lifecycle::Active));
+        assert!(!last.removed);
+    }
+
+    fn sample(at: Instant, battery: f32) -> Sample {
+        Sample {
+            at,
+            battery,
+            z: 0.0,
+            speed: None,
+            rate_hz: 0.0,
+        }
+    }
+
+    #[test]
+    fn history_records_once_per_period() {
+        let t0 = Instant::now();
+        let ms = |n: u64| t0 + Duration::from_millis(n);
+        let mut h = History::default();
+        for n in [0, 100, 499, 500, 700, 1000] {
+            h.record(sample(ms(n), n as f32));
+        }
+        let kept: Vec<f32> = h.samples().iter().map(|s| s.battery).collect();
+        assert_eq!(kept, [0.0, 500.0, 1000.0]);
+    }
+
+    #[test]
+    fn full_history_halves_and_slows_down() {
+        let t0 = Instant::now();
+        let mut h = History::default();
+        let period = HISTORY_PERIOD.as_millis() as u64;
+        for i in 0..=HISTORY_MAX_SAMPLES as u64 {
+            h.record(sample(t0 + Duration::from_millis(i * period), i as f32));
+        }
+        assert_eq!(h.samples().len(), HISTORY_MAX_SAMPLES / 2 + 1);
+        assert_eq!(h.period(), HISTORY_PERIOD * 2);
+        // Every other sample survives, the oldest included
+        assert_eq!(h.samples()[0].battery, 0.0);
+        assert_eq!(h.samples()[1].battery, 2.0);
+
+        // The new period applies from now on
+        let last = h.samples().back().unwrap().at;
+        h.record(sample(last + HISTORY_PERIOD, -1.0));
+        assert_eq!(h.samples().back().unwrap().at, last);
+        h.record(sample(last + HISTORY_PERIOD * 2, -2.0));
+        assert_eq!(h.samples().back().unwrap().battery, -2.0);
+    }
+
+    #[test]
+    fn late_samples_are_slotted_in_order() {
+        let t0 = Instant::now();
+        let ms = |n: u64| t0 + Duration::from_millis(n);
+        let mut h = History::default();
+        for n in [0, 1000, 2000, 3000] {
+            h.record(sample(ms(n), n as f32));
+        }
+        h.insert(sample(ms(1500), 1500.0));
+        h.insert(sample(ms(500), 500.0));
+        // Too close to a sample already there
+        h.insert(sample(ms(2200), 2200.0));
+        let kept: Vec<f32> = h.samples().iter().map(|s| s.battery).collect();
+        assert_eq!(kept, [0.0, 500.0, 1000.0, 1500.0, 2000.0, 3000.0]);
+    }
+}
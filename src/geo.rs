@@ -0,0 +1,221 @@
+//! WGS84 geodetic coordinates and the local east-north-up (ENU) frame around a
+//! home point.
+//!
+//! The map and the fusion code work in planar metres; packets that carry
+//! latitude/longitude are projected into ENU through ECEF, which stays accurate
+//! well beyond the few kilometres a drone fleet covers.
+
+use std::{fmt, str::FromStr};
+
+// WGS84 ellipsoid
+const A: f64 = 6_378_137.0;
+const F: f64 = 1.0 / 298.257_223_563;
+const E2: f64 = F * (2.0 - F);
+
+/// Latitude and longitude in degrees, altitude in metres above the ellipsoid.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Geodetic {
+    pub lat: f64,
+    pub lon: f64,
+    pub alt: f64,
+}
+
+impl Geodetic {
+    pub fn new(lat: f64, lon: f64, alt: f64) -> Self {
+        Self { lat, lon, alt }
+    }
+
+    /// Earth-centred, earth-fixed coordinates in metres.
+    pub fn to_ecef(&self) -> [f64; 3] {
+        let (slat, clat) = self.lat.to_radians().sin_cos();
+        let (slon, clon) = self.lon.to_radians().sin_cos();
+        let n = A / (1.0 - E2 * slat * slat).sqrt();
+        [
+            (n + self.alt) * clat * clon,
+            (n + self.alt) * clat * slon,
+            (n * (1.0 - E2) + self.alt) * slat,
+        ]
+    }
+
+    /// Inverse of [`Geodetic::to_ecef`] (a few fixed-point iterations on latitude).
+    pub fn from_ecef(p: [f64; 3]) -> Self {
+        let [x, y, z] = p;
+        let lon = y.atan2(x);
+        let r = x.hypot(y);
+        let mut lat = z.atan2(r * (1.0 - E2));
+        let mut alt = 0.0;
+        for _ in 0..5 {
+            let slat = lat.sin();
+            let n = A / (1.0 - E2 * slat * slat).sqrt();
+            alt = r / lat.cos() - n;
+            lat = z.atan2(r * (1.0 - E2 * n / (n + alt)));
+        }
+        Self {
+            lat: lat.to_degrees(),
+            lon: lon.to_degrees(),
+            alt,
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.lat.is_finite()
+            && self.lon.is_finite()
+            && self.alt.is_finite()
+            && (-90.0..=90.0).contains(&self.lat)
+            && (-180.0..=180.0).contains(&self.lon)
+    }
+}
+
+impl fmt::Display for Geodetic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.6},{:.6},{:.1}", self.lat, self.lon, self.alt)
+    }
+}
+
+/// Parses `LAT,LON` or `LAT,LON,ALT` (degrees, degrees, metres).
+impl FromStr for Geodetic {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<f64> = s
+            .split(',')
+            .map(|v| v.trim().parse::<f64>().map_err(|e| format!("{v:?}: {e}")))
+            .collect::<Result<_, _>>()?;
+        let g = match parts[..] {
+            [lat, lon] => Geodetic::new(lat, lon, 0.0),
+            [lat, lon, alt] => Geodetic::new(lat, lon, alt),
+            _ => return Err("expected LAT,LON or LAT,LON,ALT".to_string()),
+        };
+        if !g.is_valid() {
+            return Err("latitude must be within ±90 and longitude within ±180".to_string());
+        }
+        Ok(g)
+    }
+}
+
+/// East-north-up frame with its origin at a home point.
+#[derive(Debug, Clone, Copy)]
+pub struct LocalFrame {
+    origin: Geodetic,
+    origin_ecef: [f64; 3],
+    sin_lat: f64,
+    cos_lat: f64,
+    sin_lon: f64,
+    cos_lon: f64,
+}
+
+impl LocalFrame {
+    pub fn new(origin: Geodetic) -> Self {
+        let (sin_lat, cos_lat) = origin.lat.to_radians().sin_cos();
+        let (sin_lon, cos_lon) = origin.lon.to_radians().sin_cos();
+        Self {
+            origin,
+            origin_ecef: origin.to_ecef(),
+            sin_lat,
+            cos_lat,
+            sin_lon,
+            cos_lon,
+        }
+    }
+
+    pub fn origin(&self) -> Geodetic {
+        self.origin
+    }
+
+    /// East, north, up in metres relative to the origin.
+    pub fn to_enu(&self, g: &Geodetic) -> [f64; 3] {
+        let p = g.to_ecef();
+        let d = [
+            p[0] - self.origin_ecef[0],
+            p[1] - self.origin_ecef[1],
+            p[2] - self.origin_ecef[2],
+        ];
+        let e = -self.sin_lon * d[0] + self.cos_lon * d[1];
+        let n = -self.sin_lat * self.cos_lon * d[0] - self.sin_lat * self.sin_lon * d[1]
+            + self.cos_lat * d[2];
+        let u = self.cos_lat * self.cos_lon * d[0]
+            + self.cos_lat * self.sin_lon * d[1]
+            + self.sin_lat * d[2];
+        [e, n, u]
+    }
+
+    pub fn from_enu(&self, enu: [f64; 3]) -> Geodetic {
+        let [e, n, u] = enu;
+        let dx = -self.sin_lon * e - self.sin_lat * self.cos_lon * n + self.cos_lat * self.cos_lon * u;
+        let dy = self.cos_lon * e - self.sin_lat * self.sin_lon * n + self.cos_lat * self.sin_lon * u;
+        let dz = self.cos_lat * n + self.sin_lat * u;
+        Geodetic::from_ecef([
+            self.origin_ecef[0] + dx,
+            self.origin_ecef[1] + dy,
+            self.origin_ecef[2] + dz,
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn distance(a: &Geodetic, b: &Geodetic) -> f64 {
+        let (pa, pb) = (a.to_ecef(), b.to_ecef());
+        ((pa[0] - pb[0]).powi(2) + (pa[1] - pb[1]).powi(2) + (pa[2] - pb[2]).powi(2)).sqrt()
+    }
+
+    #[test]
+    fn ecef_of_known_points() {
+        let [x, y, z] = Geodetic::new(0.0, 0.0, 0.0).to_ecef();
+        assert!((x - A).abs() < 1e-6 && y.abs() < 1e-6 && z.abs() < 1e-6);
+        // North pole sits on the semi-minor axis
+        let [x, y, z] = Geodetic::new(90.0, 0.0, 0.0).to_ecef();
+        assert!(x.abs() < 1e-6 && y.abs() < 1e-6);
+        assert!((z - A * (1.0 - F)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn geodetic_ecef_round_trip() {
+        for g in [
+            Geodetic::new(47.397_742, 8.545_594, 488.0),
+            Geodetic::new(-33.868_82, 151.209_29, 12.5),
+            Geodetic::new(64.1, -21.9, -30.0),
+        ] {
+            let back = Geodetic::from_ecef(g.to_ecef());
+            assert!(distance(&g, &back) < 1e-3, "{g} -> {back}");
+            assert!((g.alt - back.alt).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn home_is_the_enu_origin() {
+        let home = Geodetic::new(47.397_742, 8.545_594, 488.0);
+        let frame = LocalFrame::new(home);
+        for c in frame.to_enu(&home) {
+            assert!(c.abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn enu_round_trip_within_a_millimetre() {
+        let frame = LocalFrame::new(Geodetic::new(47.397_742, 8.545_594, 488.0));
+        for enu in [[120.0, -45.0, 30.0], [-2_500.0, 4_000.0, 150.0], [0.0, 0.0, -10.0]] {
+            let g = frame.from_enu(enu);
+            let back = frame.to_enu(&g);
+            for (a, b) in enu.iter().zip(back) {
+                assert!((a - b).abs() < 1e-3, "{enu:?} -> {back:?}");
+            }
+        }
+        // And the other way: a known point near home, through ENU and back
+        let point = Geodetic::new(47.398_5, 8.546_8, 520.0);
+        let back = frame.from_enu(frame.to_enu(&point));
+        assert!(distance(&point, &back) < 1e-3);
+    }
+
+    #[test]
+    fn enu_axes_point_east_and_north() {
+        let frame = LocalFrame::new(Geodetic::new(0.0, 0.0, 0.0));
+        // 0.001° of longitude at the equator is about 111 m east
+        let [e, n, _] = frame.to_enu(&Geodetic::new(0.0, 0.001, 0.0));
+        assert!((e - 111.32).abs() < 0.01 && n.abs() < 1e-6);
+        let [e, n, _] = frame.to_enu(&Geodetic::new(0.001, 0.0, 0.0));
+        assert!(e.abs() < 1e-6 && (n - 110.57).abs() < 0.01);
+    }
+}
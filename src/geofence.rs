@@ -0,0 +1,389 @@
+//! Geofence zones and breach detection.
+//!
+//! Zones are polygons or circles in world coordinates (ENU metres from the home
+//! point when the fleet reports lat/lon), each either an inclusion zone drones must
+//! stay inside or an exclusion zone they must stay out of. Optional altitude
+//! limits bound an inclusion zone vertically, or restrict an exclusion zone to a
+//! band of altitudes.
+//!
+//! ```json
+//! {
+//!   "fences": [
+//!     { "name": "Field", "kind": "inclusion", "ceiling": 120,
+//!       "polygon": [[-150, -150], [150, -150], [150, 150], [-150, 150]] },
+//!     { "name": "Mast", "kind": "exclusion", "ceiling": 60,
+//!       "circle": { "center": [40, 25], "radius": 12 } }
+//!   ]
+//! }
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::{fmt, fs, io, path::Path};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FenceKind {
+    /// Drones must stay inside.
+    Inclusion,
+    /// Drones must stay outside.
+    Exclusion,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FenceShape {
+    /// Vertices in order, implicitly closed.
+    Polygon(Vec<[f32; 2]>),
+    Circle { center: [f32; 2], radius: f32 },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Geofence {
+    pub name: String,
+    pub kind: FenceKind,
+    #[serde(flatten)]
+    pub shape: FenceShape,
+    /// Lowest allowed (inclusion) or restricted (exclusion) altitude.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub floor: Option<f32>,
+    /// Highest allowed (inclusion) or restricted (exclusion) altitude.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ceiling: Option<f32>,
+}
+
+/// How a position violates a fence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BreachKind {
+    /// Left an inclusion zone.
+    Outside,
+    /// Entered an exclusion zone.
+    Inside,
+    /// Above an inclusion zone's ceiling.
+    AboveCeiling,
+    /// Below an inclusion zone's floor.
+    BelowFloor,
+}
+
+impl BreachKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            BreachKind::Outside => "outside",
+            BreachKind::Inside => "inside",
+            BreachKind::AboveCeiling => "above ceiling of",
+            BreachKind::BelowFloor => "below floor of",
+        }
+    }
+}
+
+impl FenceShape {
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        match self {
+            FenceShape::Circle { center, radius } => {
+                (x - center[0]).hypot(y - center[1]) <= *radius
+            }
+            FenceShape::Polygon(pts) => {
+                // Even-odd ray casting
+                let mut inside = false;
+                let mut j = pts.len().wrapping_sub(1);
+                for i in 0..pts.len() {
+                    let (a, b) = (pts[i], pts[j]);
+                    if (a[1] > y) != (b[1] > y)
+                        && x < (b[0] - a[0]) * (y - a[1]) / (b[1] - a[1]) + a[0]
+                    {
+                        inside = !inside;
+                    }
+                    j = i;
+                }
+                inside
+            }
+        }
+    }
+
+    /// A point to hang the label on: the circle center or the vertex average.
+    pub fn label_point(&self) -> [f32; 2] {
+        match self {
+            FenceShape::Circle { center, .. } => *center,
+            FenceShape::Polygon(pts) => {
+                let n = pts.len().max(1) as f32;
+                let sx: f32 = pts.iter().map(|p| p[0]).sum();
+                let sy: f32 = pts.iter().map(|p| p[1]).sum();
+                [sx / n, sy / n]
+            }
+        }
+    }
+
+    /// Triangulation of a simple polygon (ear clipping), as vertex index triples.
+    /// Circles and degenerate polygons yield nothing.
+    pub fn triangles(&self) -> Vec<[usize; 3]> {
+        let FenceShape::Polygon(pts) = self else {
+            return Vec::new();
+        };
+        if pts.len() < 3 {
+            return Vec::new();
+        }
+        let cross = |o: [f32; 2], a: [f32; 2], b: [f32; 2]| {
+            (a[0] - o[0]) * (b[1] - o[1]) - (a[1] - o[1]) * (b[0] - o[0])
+        };
+        // Work counter-clockwise so ears are the convex (positive cross) corners
+        let area: f32 = (0..pts.len())
+            .map(|i| cross([0.0, 0.0], pts[i], pts[(i + 1) % pts.len()]))
+            .sum();
+        let mut idx: Vec<usize> = (0..pts.len()).collect();
+        if area < 0.0 {
+            idx.reverse();
+        }
+
+        let mut out = Vec::with_capacity(pts.len() - 2);
+        let mut guard = 0;
+        while idx.len() > 3 && guard < pts.len() * pts.len() {
+            guard += 1;
+            let n = idx.len();
+            let ear = (0..n).find(|&i| {
+                let (a, b, c) = (idx[(i + n - 1) % n], idx[i], idx[(i + 1) % n]);
+                if cross(pts[a], pts[b], pts[c]) <= 0.0 {
+                    return false;
+                }
+                idx.iter().all(|&p| {
+                    p == a
+                        || p == b
+                        || p == c
+                        || cross(pts[a], pts[b], pts[p]) < 0.0
+                        || cross(pts[b], pts[c], pts[p]) < 0.0
+                        || cross(pts[c], pts[a], pts[p]) < 0.0
+                })
+            });
+            // Self-intersecting input has no ear; clip anyway to terminate
+            let i = ear.unwrap_or(0);
+            out.push([idx[(i + n - 1) % n], idx[i], idx[(i + 1) % n]]);
+            idx.remove(i);
+        }
+        if idx.len() == 3 {
+            out.push([idx[0], idx[1], idx[2]]);
+        }
+        out
+    }
+}
+
+impl Geofence {
+    /// The breach, if any, of a drone at `x`, `y`, `z`.
+    pub fn check(&self, x: f32, y: f32, z: f32) -> Option<BreachKind> {
+        let inside = self.shape.contains(x, y);
+        match self.kind {
+            FenceKind::Inclusion => {
+                if !inside {
+                    Some(BreachKind::Outside)
+                } else if self.ceiling.is_some_and(|c| z > c) {
+                    Some(BreachKind::AboveCeiling)
+                } else if self.floor.is_some_and(|f| z < f) {
+                    Some(BreachKind::BelowFloor)
+                } else {
+                    None
+                }
+            }
+            FenceKind::Exclusion => {
+                let in_band =
+                    self.floor.is_none_or(|f| z >= f) && self.ceiling.is_none_or(|c| z <= c);
+                (inside && in_band).then_some(BreachKind::Inside)
+            }
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        match &self.shape {
+            FenceShape::Polygon(pts) if pts.len() < 3 => {
+                Err(format!("polygon {:?} needs at least 3 points", self.name))
+            }
+            FenceShape::Circle { radius, .. } if radius.is_nan() || *radius <= 0.0 => {
+                Err(format!("circle {:?} needs a positive radius", self.name))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum GeofenceError {
+    Io(io::Error),
+    Parse(serde_json::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for GeofenceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeofenceError::Io(e) => write!(f, "cannot read geofences: {e}"),
+            GeofenceError::Parse(e) => write!(f, "invalid geofence file: {e}"),
+            GeofenceError::Invalid(msg) => write!(f, "invalid geofence: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for GeofenceError {}
+
+#[derive(Serialize, Deserialize)]
+struct FenceFile {
+    fences: Vec<Geofence>,
+}
+
+pub fn load_fences(path: impl AsRef<Path>) -> Result<Vec<Geofence>, GeofenceError> {
+    let text = fs::read_to_string(path).map_err(GeofenceError::Io)?;
+    let file: FenceFile = serde_json::from_str(&text).map_err(GeofenceError::Parse)?;
+    for f in &file.fences {
+        f.validate().map_err(GeofenceError::Invalid)?;
+    }
+    Ok(file.fences)
+}
+
+pub fn save_fences(path: impl AsRef<Path>, fences: &[Geofence]) -> Result<(), GeofenceError> {
+    let file = FenceFile {
+        fences: fences.to_vec(),
+    };
+    let text = serde_json::to_string_pretty(&file).map_err(GeofenceError::Parse)?;
+    fs::write(path, text).map_err(GeofenceError::Io)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A U open to the north: the notch between x = 10 and 20 reaches down to y = 10.
+    fn u_shape() -> Vec<[f32; 2]> {
+        vec![
+            [0.0, 0.0],
+            [30.0, 0.0],
+            [30.0, 30.0],
+            [20.0, 30.0],
+            [20.0, 10.0],
+            [10.0, 10.0],
+            [10.0, 30.0],
+            [0.0, 30.0],
+        ]
+    }
+
+    fn fence(kind: FenceKind, shape: FenceShape) -> Geofence {
+        Geofence {
+            name: "Zone".into(),
+            kind,
+            shape,
+            floor: None,
+            ceiling: None,
+        }
+    }
+
+    #[test]
+    fn concave_polygon_contains() {
+        let u = FenceShape::Polygon(u_shape());
+        for (x, y) in [(5.0, 20.0), (25.0, 25.0), (15.0, 5.0)] {
+            assert!(u.contains(x, y), "({x}, {y}) should be inside");
+        }
+        for (x, y) in [(15.0, 20.0), (40.0, 5.0), (-1.0, 15.0), (15.0, 31.0)] {
+            assert!(!u.contains(x, y), "({x}, {y}) should be outside");
+        }
+        let circle = FenceShape::Circle {
+            center: [0.0, 0.0],
+            radius: 5.0,
+        };
+        assert!(circle.contains(3.0, 4.0));
+        assert!(!circle.contains(3.0, 4.1));
+    }
+
+    #[test]
+    fn triangles_cover_the_polygon_either_way_round() {
+        let area = |pts: &[[f32; 2]], t: &[usize; 3]| {
+            let [a, b, c] = t.map(|i| pts[i]);
+            ((b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])).abs() / 2.0
+        };
+        let ccw = u_shape();
+        let mut cw = u_shape();
+        cw.reverse();
+        for pts in [ccw, cw] {
+            let tris = FenceShape::Polygon(pts.clone()).triangles();
+            assert_eq!(tris.len(), pts.len() - 2);
+            let total: f32 = tris.iter().map(|t| area(&pts, t)).sum();
+            assert!((total - 700.0).abs() < 1e-3, "area {total}");
+        }
+        let circle = FenceShape::Circle {
+            center: [0.0, 0.0],
+            radius: 1.0,
+        };
+        assert!(circle.triangles().is_empty());
+        assert!(FenceShape::Polygon(vec![[0.0, 0.0], [1.0, 0.0]]).triangles().is_empty());
+    }
+
+    #[test]
+    fn inclusion_limits_and_exclusion_band() {
+        let field = Geofence {
+            floor: Some(5.0),
+            ceiling: Some(120.0),
+            ..fence(FenceKind::Inclusion, FenceShape::Polygon(u_shape()))
+        };
+        assert_eq!(field.check(5.0, 20.0, 50.0), None);
+        assert_eq!(field.check(15.0, 20.0, 50.0), Some(BreachKind::Outside));
+        assert_eq!(field.check(5.0, 20.0, 121.0), Some(BreachKind::AboveCeiling));
+        assert_eq!(field.check(5.0, 20.0, 4.0), Some(BreachKind::BelowFloor));
+
+        let mast = Geofence {
+            floor: Some(10.0),
+            ceiling: Some(60.0),
+            ..fence(
+                FenceKind::Exclusion,
+                FenceShape::Circle {
+                    center: [0.0, 0.0],
+                    radius: 12.0,
+                },
+            )
+        };
+        assert_eq!(mast.check(0.0, 0.0, 30.0), Some(BreachKind::Inside));
+        // Under and over the restricted band, or beside it
+        assert_eq!(mast.check(0.0, 0.0, 5.0), None);
+        assert_eq!(mast.check(0.0, 0.0, 61.0), None);
+        assert_eq!(mast.check(20.0, 0.0, 30.0), None);
+    }
+
+    #[test]
+    fn validate_rejects_degenerate_shapes() {
+        let line = fence(FenceKind::Inclusion, FenceShape::Polygon(vec![[0.0; 2], [1.0; 2]]));
+        assert!(line.validate().is_err());
+        for radius in [0.0, -1.0, f32::NAN] {
+            let dot = fence(
+                FenceKind::Exclusion,
+                FenceShape::Circle {
+                    center: [0.0; 2],
+                    radius,
+                },
+            );
+            assert!(dot.validate().is_err());
+        }
+        assert!(fence(FenceKind::Inclusion, FenceShape::Polygon(u_shape())).validate().is_ok());
+    }
+
+    #[test]
+    fn save_then_load_round_trips() {
+        let path = std::env::temp_dir().join(format!("fences-{}.json", std::process::id()));
+        let fences = vec![
+            Geofence {
+                ceiling: Some(120.0),
+                ..fence(FenceKind::Inclusion, FenceShape::Polygon(u_shape()))
+            },
+            Geofence {
+                name: "Mast".into(),
+                floor: Some(10.0),
+                ..fence(
+                    FenceKind::Exclusion,
+                    FenceShape::Circle {
+                        center: [40.0, 25.0],
+                        radius: 12.0,
+                    },
+                )
+            },
+        ];
+        save_fences(&path, &fences).unwrap();
+        assert_eq!(load_fences(&path).unwrap(), fences);
+
+        fs::write(&path, r#"{ "fences": [ { "name": "Dot", "kind": "exclusion",
+            "circle": { "center": [0, 0], "radius": 0 } } ] }"#)
+        .unwrap();
+        assert!(matches!(load_fences(&path), Err(GeofenceError::Invalid(_))));
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,19 @@
+//! Shared pieces of the Telemetry Fusion Dashboard.
+//!
+//! Both binaries link against this crate: the simulator encodes [`telemetry::Telemetry`]
+//! packets and the dashboard decodes them and folds them into [`fusion::AppState`].
+
+pub mod alerts;
+pub mod diagnostics;
+pub mod flight;
+pub mod fusion;
+pub mod geo;
+pub mod geofence;
+pub mod link;
+pub mod record;
+pub mod replay;
+pub mod scenario;
+pub mod telemetry;
+pub mod tiles;
+pub mod track;
+pub mod wire;
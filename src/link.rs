@@ -0,0 +1,197 @@
+//! Per-drone radio link health derived from packet arrivals.
+//!
+//! Loss and reordering come from the optional per-drone `seq` field; rate, jitter
+//! and clock offset work for every sender. A sequence number far outside the
+//! expected range resets the tracking, as when a sender restarts its counter.
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// Window over which the received packet rate is measured.
+pub const RATE_WINDOW: Duration = Duration::from_secs(5);
+
+// Sequence jumps treated as the sender restarting its counter rather than as loss
+// or reordering: further back than the 64-number reorder window, or further ahead
+// than any plausible outage
+pub const SEQ_RESTART_BEHIND: i64 = 64;
+pub const SEQ_RESTART_AHEAD: i64 = 1 << 16;
+
+#[derive(Debug, Clone, Default)]
+pub struct LinkStats {
+    /// Packets received, including late and duplicate ones.
+    pub received: u64,
+    /// Sequence numbers skipped and not (yet) seen.
+    pub lost: u64,
+    /// Packets that arrived after a higher sequence number had already been seen.
+    pub out_of_order: u64,
+    /// Packets whose sequence number was already received.
+    pub duplicates: u64,
+    /// Times the sequence jumped far enough to count as a sender restart.
+    pub restarts: u64,
+    /// Interarrival jitter in ms (RFC 3550 estimator on receive vs send times).
+    pub jitter_ms: f64,
+    /// Smoothed `receive wall time - ts_ms`: sender clock skew plus one-way latency.
+    pub clock_offset_ms: f64,
+
+    highest_seq: Option<u32>,
+    /// Bit `i` set = `highest_seq - i` was received (covers the last 64 numbers).
+    seen_mask: u64,
+    prev_transit_ms: Option<f64>,
+    arrivals: VecDeque<Instant>,
+}
+
+impl LinkStats {
+    /// Account for one packet stamped `ts_ms` that arrived at `at` (`recv_ms` wall time).
+    pub fn on_packet(&mut self, seq: Option<u32>, ts_ms: u128, recv_ms: u128, at: Instant) {
+        self.received += 1;
+
+        if let Some(seq) = seq {
+            match self.highest_seq {
+                None => {
+                    self.highest_seq = Some(seq);
+                    self.seen_mask = 1;
+                }
+                Some(high) => {
+                    // Signed distance copes with u32 wrap-around; widened so that
+                    // negating i32::MIN can't overflow
+                    let delta = seq.wrapping_sub(high) as i32 as i64;
+                    if delta > SEQ_RESTART_AHEAD || delta <= -SEQ_RESTART_BEHIND {
+                        self.restarts += 1;
+                        self.highest_seq = Some(seq);
+                        self.seen_mask = 1;
+                    } else if delta > 0 {
+                        self.lost += (delta - 1) as u64;
+                        self.highest_seq = Some(seq);
+                        self.seen_mask = if delta < 64 {
+                            (self.seen_mask << delta) | 1
+                        } else {
+                            1
+                        };
+                    } else {
+                        let bit = 1u64 << -delta;
+                        if self.seen_mask & bit != 0 {
+                            self.duplicates += 1;
+                        } else {
+                            // Late arrival: it was counted as lost when skipped over
+                            self.seen_mask |= bit;
+                            self.out_of_order += 1;
+                            self.lost = self.lost.saturating_sub(1);
+                        }
+                    }
+                }
+            }
+        }
+
+        let transit = recv_ms as f64 - ts_ms as f64;
+        if let Some(prev) = self.prev_transit_ms {
+            let d = (transit - prev).abs();
+            self.jitter_ms += (d - self.jitter_ms) / 16.0;
+            self.clock_offset_ms += 0.1 * (transit - self.clock_offset_ms);
+        } else {
+            self.clock_offset_ms = transit;
+        }
+        self.prev_transit_ms = Some(transit);
+
+        self.arrivals.push_back(at);
+        while let Some(&first) = self.arrivals.front() {
+            if at.saturating_duration_since(first) > RATE_WINDOW {
+                self.arrivals.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Packets per second over the last [`RATE_WINDOW`], as seen at `now`.
+    pub fn rate_hz(&self, now: Instant) -> f32 {
+        let recent = self
+            .arrivals
+            .iter()
+            .filter(|&&t| now.saturating_duration_since(t) <= RATE_WINDOW)
+            .count();
+        recent as f32 / RATE_WINDOW.as_secs_f32()
+    }
+
+    /// Fraction of sequenced packets lost, 0..1.
+    pub fn loss_ratio(&self) -> f32 {
+        let expected = self.received - self.duplicates + self.lost;
+        if expected == 0 {
+            0.0
+        } else {
+            self.lost as f32 / expected as f32
+        }
+    }
+
+    /// Whether the sender provides sequence numbers (loss figures are meaningful).
+    pub fn sequenced(&self) -> bool {
+        self.highest_seq.is_some()
+    }
+
+    /// Single 0..1 score for display: penalizes loss, reordering and jitter.
+    pub fn quality(&self) -> f32 {
+        let reorder = if self.received == 0 {
+            0.0
+        } else {
+            self.out_of_order as f32 / self.received as f32
+        };
+        let jitter_penalty = (self.jitter_ms as f32 / 200.0).min(1.0);
+        (1.0 - 2.0 * self.loss_ratio() - reorder - 0.3 * jitter_penalty).clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(seqs: impl IntoIterator<Item = u32>) -> LinkStats {
+        let mut link = LinkStats::default();
+        let at = Instant::now();
+        for seq in seqs {
+            link.on_packet(Some(seq), 0, 0, at);
+        }
+        link
+    }
+
+    #[test]
+    fn in_order_stream_is_clean() {
+        let link = feed(0..100);
+        assert_eq!((link.lost, link.out_of_order, link.duplicates), (0, 0, 0));
+        assert_eq!(link.loss_ratio(), 0.0);
+    }
+
+    #[test]
+    fn wraps_around_u32() {
+        let link = feed([u32::MAX - 1, u32::MAX, 0, 1]);
+        assert_eq!((link.lost, link.out_of_order, link.restarts), (0, 0, 0));
+    }
+
+    #[test]
+    fn gap_then_late_arrival() {
+        let link = feed([0, 1, 4, 2, 5]);
+        assert_eq!(link.out_of_order, 1);
+        assert_eq!(link.lost, 1); // 3 never came
+    }
+
+    #[test]
+    fn duplicates_are_counted_once() {
+        let link = feed([0, 1, 1, 2, 1]);
+        assert_eq!(link.duplicates, 2);
+        assert_eq!((link.lost, link.out_of_order), (0, 0));
+    }
+
+    #[test]
+    fn counter_restart_resyncs() {
+        let link = feed((0..500).chain(0..50));
+        assert_eq!(link.restarts, 1);
+        assert_eq!((link.lost, link.out_of_order, link.duplicates), (0, 0, 0));
+    }
+
+    #[test]
+    fn half_range_jump_does_not_overflow() {
+        let link = feed([0, 0x8000_0000, 0x8000_0001]);
+        assert_eq!(link.restarts, 1);
+        assert_eq!(link.lost, 0);
+    }
+}
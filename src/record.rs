@@ -0,0 +1,288 @@
+//! Append-only session logs of received datagrams.
+//!
+//! A log file starts with an 8-byte magic header followed by length-prefixed records,
+//! integers little-endian:
+//!
+//! ```text
+//! record:
+//!      0     4  length of the rest of the record
+//!      4     8  receive time, ms since the Unix epoch
+//!     12     1  source address length N
+//!     13     N  source address, UTF-8 ("ip:port")
+//!   13+N     *  raw datagram, exactly as received
+//! ```
+//!
+//! Each record is written with a single `write_all`, and readers stop at the first
+//! record whose declared length runs past the end of the file. A crash mid-write
+//! therefore loses at most the record being written, and appending to such a file
+//! again is safe because the writer trims the torn tail before resuming.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufReader, Read, Seek, SeekFrom, Write},
+    net::SocketAddr,
+    path::{Path, PathBuf},
+};
+
+pub const LOG_MAGIC: [u8; 8] = *b"TFLOG\0\x01\0";
+
+/// Longest possible record after the length prefix: receive time, the longest
+/// address and the largest UDP datagram. Anything longer is corruption.
+pub const MAX_RECORD_LEN: usize = 8 + 1 + u8::MAX as usize + u16::MAX as usize;
+
+/// One datagram as it arrived at a listener.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogRecord {
+    pub recv_ms: u64,
+    pub source: String,
+    pub payload: Vec<u8>,
+}
+
+/// Appends records to a session log.
+pub struct LogWriter {
+    file: File,
+    path: PathBuf,
+    records: u64,
+}
+
+impl LogWriter {
+    /// Open `path` for appending, creating it (with header) if needed.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+
+        let len = file.metadata()?.len();
+        if len == 0 {
+            file.write_all(&LOG_MAGIC)?;
+        } else {
+            // Validate the header and drop any torn record left by a crash
+            let mut reader = LogReader::new(BufReader::new(file.try_clone()?))?;
+            for r in reader.by_ref() {
+                r?;
+            }
+            let good_len = reader.offset();
+            if good_len < len {
+                file.set_len(good_len)?;
+            }
+        }
+        file.seek(SeekFrom::End(0))?;
+
+        Ok(Self {
+            file,
+            path,
+            records: 0,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Number of records written through this writer.
+    pub fn records(&self) -> u64 {
+        self.records
+    }
+
+    pub fn append(&mut self, recv_ms: u64, source: SocketAddr, payload: &[u8]) -> io::Result<()> {
+        let source = source.to_string();
+        let source = &source.as_bytes()[..source.len().min(u8::MAX as usize)];
+        let rest_len = 8 + 1 + source.len() + payload.len();
+
+        let mut buf = Vec::with_capacity(4 + rest_len);
+        buf.extend_from_slice(&(rest_len as u32).to_le_bytes());
+        buf.extend_from_slice(&recv_ms.to_le_bytes());
+        buf.push(source.len() as u8);
+        buf.extend_from_slice(source);
+        buf.extend_from_slice(payload);
+
+        self.file.write_all(&buf)?;
+        self.records += 1;
+        Ok(())
+    }
+}
+
+/// Iterates the records of a session log.
+///
+/// Iteration ends at end of file or at a truncated trailing record; check
+/// [`LogReader::truncated`] afterwards to tell the two apart.
+pub struct LogReader<R> {
+    inner: R,
+    offset: u64,
+    truncated: bool,
+}
+
+impl LogReader<BufReader<File>> {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> LogReader<R> {
+    pub fn new(mut inner: R) -> io::Result<Self> {
+        let mut magic = [0u8; 8];
+        inner.read_exact(&mut magic)?;
+        if magic != LOG_MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a telemetry session log",
+            ));
+        }
+        Ok(Self {
+            inner,
+            offset: LOG_MAGIC.len() as u64,
+            truncated: false,
+        })
+    }
+
+    /// Byte offset just past the last complete record read.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// True if iteration stopped on a partially written record.
+    pub fn truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Read exactly `buf.len()` bytes; `Ok(false)` on a clean or torn end of file.
+    fn fill(&mut self, buf: &mut [u8]) -> io::Result<bool> {
+        let mut got = 0;
+        while got < buf.len() {
+            match self.inner.read(&mut buf[got..]) {
+                Ok(0) => {
+                    self.truncated |= got > 0;
+                    return Ok(false);
+                }
+                Ok(n) => got += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(true)
+    }
+
+    fn read_record(&mut self) -> io::Result<Option<LogRecord>> {
+        let mut len = [0u8; 4];
+        if !self.fill(&mut len)? {
+            return Ok(None);
+        }
+        let rest_len = u32::from_le_bytes(len) as usize;
+        // A garbage length is treated like a torn tail rather than allocated
+        if rest_len > MAX_RECORD_LEN {
+            self.truncated = true;
+            return Ok(None);
+        }
+        let mut rest = vec![0u8; rest_len];
+        if !self.fill(&mut rest)? {
+            self.truncated = true;
+            return Ok(None);
+        }
+        if rest_len < 9 || rest_len < 9 + rest[8] as usize {
+            self.truncated = true;
+            return Ok(None);
+        }
+
+        let recv_ms = u64::from_le_bytes(rest[..8].try_into().unwrap());
+        let addr_len = rest[8] as usize;
+        let source = String::from_utf8_lossy(&rest[9..9 + addr_len]).into_owned();
+        let payload = rest[9 + addr_len..].to_vec();
+
+        self.offset += 4 + rest_len as u64;
+        Ok(Some(LogRecord {
+            recv_ms,
+            source,
+            payload,
+        }))
+    }
+}
+
+impl<R: Read> Iterator for LogReader<R> {
+    type Item = io::Result<LogRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
+
+/// Read a whole log into memory, ignoring a torn trailing record.
+pub fn read_log(path: impl AsRef<Path>) -> io::Result<Vec<LogRecord>> {
+    LogReader::open(path)?.collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh log path under the system temp directory.
+    fn temp_log(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("tflog-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn write_records(path: &Path, n: u64) -> Vec<LogRecord> {
+        let addr: SocketAddr = "127.0.0.1:5000".parse().unwrap();
+        let mut w = LogWriter::open(path).unwrap();
+        (0..n)
+            .map(|i| {
+                let payload = format!("{{\"n\":{i}}}").into_bytes();
+                w.append(1_000 + i, addr, &payload).unwrap();
+                LogRecord {
+                    recv_ms: 1_000 + i,
+                    source: addr.to_string(),
+                    payload,
+                }
+            })
+            .collect()
+    }
+
+    fn append_raw(path: &Path, bytes: &[u8]) {
+        let mut f = OpenOptions::new().append(true).open(path).unwrap();
+        f.write_all(bytes).unwrap();
+    }
+
+    #[test]
+    fn records_round_trip() {
+        let path = temp_log("round-trip");
+        let written = write_records(&path, 3);
+        assert_eq!(read_log(&path).unwrap(), written);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn torn_tail_loads_good_prefix_and_is_trimmed() {
+        let path = temp_log("torn");
+        let written = write_records(&path, 2);
+        // Length says 40 bytes, only 5 follow
+        append_raw(&path, &[40, 0, 0, 0, 1, 2, 3, 4, 5]);
+
+        let mut reader = LogReader::open(&path).unwrap();
+        let read: Vec<LogRecord> = reader.by_ref().map(Result::unwrap).collect();
+        assert_eq!(read, written);
+        assert!(reader.truncated());
+
+        // Reopening for append drops the torn record before writing after it
+        let mut w = LogWriter::open(&path).unwrap();
+        w.append(9, "127.0.0.1:5000".parse().unwrap(), b"x").unwrap();
+        assert_eq!(read_log(&path).unwrap().len(), 3);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn garbage_length_is_not_allocated() {
+        let path = temp_log("garbage");
+        let written = write_records(&path, 2);
+        append_raw(&path, &[0xf0, 0xff, 0xff, 0xff, 0, 0, 0, 0]);
+
+        let mut reader = LogReader::open(&path).unwrap();
+        let read: Vec<LogRecord> = reader.by_ref().map(Result::unwrap).collect();
+        assert_eq!(read, written);
+        assert!(reader.truncated());
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,324 @@
+//! Playback of a recorded session into an [`AppState`].
+//!
+//! Packets are paced by their own `ts_ms`, not by when they were received. Each
+//! `ts_ms` is mapped onto a synthetic [`Instant`] so the fusion code (EMA, trail
+//! pruning) and the UI's age computations behave exactly as they would live.
+
+use crate::{
+    fusion::{AppState, Arrival},
+    record::read_log,
+    telemetry::{self, Telemetry},
+};
+use std::{
+    io,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+pub const SPEEDS: [f32; 7] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0, 16.0];
+
+/// A decoded packet and the wall-clock time it was originally received.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub recv_ms: u128,
+    /// Address the packet came from; replayed as its source.
+    pub source: String,
+    pub telemetry: Telemetry,
+}
+
+pub struct Replay {
+    frames: Vec<Frame>,
+    /// Instant that corresponds to `start_ms`.
+    base: Instant,
+    start_ms: u128,
+    end_ms: u128,
+
+    /// Current playback position in `ts_ms` units (fractional so slow speeds advance).
+    cursor_ms: f64,
+    /// Number of frames already folded into the state.
+    applied: usize,
+
+    pub playing: bool,
+    pub speed: f32,
+    last_tick: Option<Instant>,
+
+    /// Records in the log that did not decode as telemetry.
+    pub skipped: usize,
+}
+
+impl Replay {
+    /// Load a session log, keeping every record that decodes as telemetry and
+    /// counting the rest in [`Replay::skipped`].
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let records = read_log(path)?;
+        let frames: Vec<Frame> = records
+            .iter()
+            .filter_map(|r| {
+                let telemetry = telemetry::decode(&r.payload).ok()?;
+                Some(Frame {
+                    recv_ms: r.recv_ms as u128,
+                    source: r.source.clone(),
+                    telemetry,
+                })
+            })
+            .collect();
+        Ok(Self {
+            skipped: records.len() - frames.len(),
+            ..Self::from_frames(frames)
+        })
+    }
+
+    pub fn from_frames(mut frames: Vec<Frame>) -> Self {
+        // Stable sort keeps arrival order for equal timestamps
+        frames.sort_by_key(|f| f.telemetry.ts_ms);
+        let start_ms = frames.first().map(|f| f.telemetry.ts_ms).unwrap_or(0);
+        let end_ms = frames.last().map(|f| f.telemetry.ts_ms).unwrap_or(0);
+        Self {
+            frames,
+            base: Instant::now(),
+            start_ms,
+            end_ms,
+            cursor_ms: start_ms as f64,
+            applied: 0,
+            playing: false,
+            speed: 1.0,
+            last_tick: None,
+            skipped: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    pub fn start_ms(&self) -> u128 {
+        self.start_ms
+    }
+
+    pub fn end_ms(&self) -> u128 {
+        self.end_ms
+    }
+
+    pub fn cursor_ms(&self) -> u128 {
+        self.cursor_ms as u128
+    }
+
+    /// Playback position relative to the first frame.
+    pub fn position(&self) -> Duration {
+        Duration::from_millis((self.cursor_ms as u128 - self.start_ms) as u64)
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_millis((self.end_ms - self.start_ms) as u64)
+    }
+
+    pub fn at_end(&self) -> bool {
+        self.applied == self.frames.len() && self.cursor_ms as u128 >= self.end_ms
+    }
+
+    /// Synthetic receive instant for a frame stamped `ts_ms`.
+    fn instant_at(&self, ts_ms: u128) -> Instant {
+        self.base + Duration::from_millis(ts_ms.saturating_sub(self.start_ms) as u64)
+    }
+
+    pub fn set_playing(&mut self, playing: bool) {
+        self.playing = playing;
+        self.last_tick = None;
+    }
+
+    /// Advance the cursor by wall-clock time scaled by `speed` and apply due frames.
+    pub fn tick(&mut self, state: &mut AppState) {
+        let now = Instant::now();
+        if self.playing {
+            if let Some(prev) = self.last_tick {
+                let dt = (now - prev).as_secs_f64() * 1000.0 * self.speed as f64;
+                self.cursor_ms = (self.cursor_ms + dt).min(self.end_ms as f64);
+            }
+            self.last_tick = Some(now);
+            if self.at_end() {
+                self.set_playing(false);
+            }
+        }
+        self.advance(state);
+    }
+
+    /// Jump to `ts_ms`, rebuilding the state from scratch when seeking backwards.
+    pub fn seek(&mut self, ts_ms: u128, state: &mut AppState) {
+        let ts_ms = ts_ms.clamp(self.start_ms, self.end_ms);
+        if (ts_ms as f64) < self.cursor_ms {
+            state.reset();
+            self.applied = 0;
+        }
+        self.cursor_ms = ts_ms as f64;
+        self.last_tick = None;
+        self.advance(state);
+    }
+
+    /// Step to the next distinct timestamp after the cursor.
+    pub fn step_forward(&mut self, state: &mut AppState) {
+        let cursor = self.cursor_ms as u128;
+        if let Some(next) = self.frames[self.applied..]
+            .iter()
+            .map(|f| f.telemetry.ts_ms)
+            .find(|&ts| ts > cursor)
+        {
+            self.seek(next, state);
+        }
+    }
+
+    /// Step to the last distinct timestamp before the cursor.
+    pub fn step_back(&mut self, state: &mut AppState) {
+        let cursor = self.cursor_ms as u128;
+        if let Some(prev) = self.frames[..self.applied]
+            .iter()
+            .rev()
+            .map(|f| f.telemetry.ts_ms)
+            .find(|&ts| ts < cursor)
+        {
+            self.seek(prev, state);
+        }
+    }
+
+    fn advance(&mut self, state: &mut AppState) {
+        while let Some(f) = self.frames.get(self.applied) {
+            let ts_ms = f.telemetry.ts_ms;
+            if ts_ms as f64 > self.cursor_ms {
+                break;
+            }
+            let arrival = Arrival {
+                at: self.instant_at(ts_ms),
+                wall_ms: f.recv_ms,
+                source: state.source_named(&f.source),
+            };
+            state.apply(f.telemetry.clone(), arrival);
+            self.applied += 1;
+        }
+        state.clock = Some(self.instant_at(self.cursor_ms as u128));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::record::LogWriter;
+
+    const T0: u128 = 1_700_000_000_000;
+
+    /// Two drones flying east at 10 Hz for `secs` seconds.
+    fn frames(secs: u128) -> Vec<Frame> {
+        (0..secs * 10)
+            .flat_map(|i| {
+                [1, 2].map(|id| Frame {
+                    recv_ms: T0 + i * 100 + 5,
+                    source: "127.0.0.1:9000".into(),
+                    telemetry: Telemetry {
+                        id,
+                        x: i as f32 * 0.5,
+                        y: id as f32 * 10.0,
+                        z: 20.0,
+                        battery: 90.0 - i as f32 * 0.01,
+                        status: "OK".into(),
+                        ts_ms: T0 + i * 100,
+                        ..Default::default()
+                    },
+                })
+            })
+            .collect()
+    }
+
+    /// Position, smoothed position and trail length of every drone.
+    fn snapshot(state: &AppState) -> Vec<(u32, [f32; 4], usize)> {
+        let mut out: Vec<_> = state
+            .drones
+            .iter()
+            .map(|(&id, d)| (id, [d.x, d.y, d.smoothed_x, d.smoothed_y], d.trail.len()))
+            .collect();
+        out.sort_by_key(|s| s.0);
+        out
+    }
+
+    #[test]
+    fn seeking_back_rebuilds_the_same_state() {
+        let mut straight = AppState::default();
+        let mut replay = Replay::from_frames(frames(10));
+        replay.seek(T0 + 8_000, &mut straight);
+
+        let mut seeked = AppState::default();
+        let mut replay = Replay::from_frames(frames(10));
+        replay.seek(T0 + 9_000, &mut seeked);
+        replay.seek(T0 + 3_000, &mut seeked);
+        assert_eq!(seeked.drones[&1].last_ts_ms, T0 + 3_000);
+        assert_eq!(seeked.total_packets, 62);
+        replay.seek(T0 + 8_000, &mut seeked);
+
+        assert_eq!(snapshot(&seeked), snapshot(&straight));
+        assert_eq!(seeked.total_packets, straight.total_packets);
+        assert_eq!(replay.cursor_ms(), T0 + 8_000);
+    }
+
+    #[test]
+    fn steps_move_between_distinct_timestamps() {
+        let mut state = AppState::default();
+        let mut replay = Replay::from_frames(frames(2));
+        replay.seek(T0, &mut state);
+        assert_eq!(state.total_packets, 2);
+
+        let mut seen = vec![replay.cursor_ms()];
+        for _ in 0..3 {
+            replay.step_forward(&mut state);
+            seen.push(replay.cursor_ms());
+        }
+        assert_eq!(seen, [T0, T0 + 100, T0 + 200, T0 + 300]);
+        assert_eq!(state.total_packets, 8);
+
+        replay.step_back(&mut state);
+        assert_eq!(replay.cursor_ms(), T0 + 200);
+        assert_eq!(state.drones[&2].last_ts_ms, T0 + 200);
+        assert_eq!(state.total_packets, 6);
+
+        // Nothing before the first frame
+        replay.seek(T0, &mut state);
+        replay.step_back(&mut state);
+        assert_eq!(replay.cursor_ms(), T0);
+    }
+
+    #[test]
+    fn playback_stops_at_the_end() {
+        let mut state = AppState::default();
+        let mut replay = Replay::from_frames(frames(2));
+        replay.set_playing(true);
+        replay.seek(replay.end_ms() + 5_000, &mut state);
+        assert_eq!(replay.cursor_ms(), replay.end_ms());
+        assert!(replay.at_end());
+        assert_eq!(state.total_packets, replay.len() as u64);
+
+        replay.tick(&mut state);
+        assert!(!replay.playing);
+        assert_eq!(state.clock, Some(replay.instant_at(replay.end_ms())));
+        replay.step_forward(&mut state);
+        assert_eq!(replay.cursor_ms(), replay.end_ms());
+    }
+
+    #[test]
+    fn load_counts_undecodable_records() {
+        let path = std::env::temp_dir().join(format!("replay-{}.tflog", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let addr = "127.0.0.1:9000".parse().unwrap();
+        let mut log = LogWriter::open(&path).unwrap();
+        for f in frames(1).iter().take(4) {
+            log.append(f.recv_ms as u64, addr, &f.telemetry.encode_json()).unwrap();
+        }
+        log.append(T0 as u64, addr, b"not telemetry").unwrap();
+        log.append(T0 as u64, addr, br#"{"id": 1}"#).unwrap();
+        drop(log);
+
+        let replay = Replay::load(&path).unwrap();
+        assert_eq!((replay.len(), replay.skipped), (4, 2));
+        assert_eq!((replay.start_ms(), replay.end_ms()), (T0, T0 + 100));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,319 @@
+use crate::wire::{self, WireFormat};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    ops::RangeInclusive,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Battery charge, percent. Like the ranges below, anything outside is a sender
+/// bug rather than a real reading.
+pub const BATTERY_RANGE: RangeInclusive<f64> = 0.0..=100.0;
+/// Local x/y, either units of the sender's frame or metres from home.
+pub const COORDINATE_RANGE: RangeInclusive<f64> = -1.0e7..=1.0e7;
+/// Local z and geodetic altitude, metres.
+pub const ALTITUDE_RANGE: RangeInclusive<f64> = -1_000.0..=20_000.0;
+/// Ground speed and each velocity component, units/s.
+pub const MAX_SPEED: f64 = 300.0;
+
+/// How far `ts_ms` may run ahead of the receiver's clock, ms. Sender clocks drift
+/// and simulated ones may start anywhere, but a stamp days ahead is a unit mistake.
+pub const MAX_CLOCK_AHEAD_MS: u128 = 24 * 60 * 60 * 1000;
+
+/// Battery percentage below which a drone reports `LOW_BAT`.
+pub const LOW_BATTERY_PCT: f32 = 15.0;
+
+/// One telemetry sample as sent by a drone (or the simulator).
+///
+/// Everything after `ts_ms` is optional: senders that predate those fields simply
+/// omit them, and they are left out of the JSON when `None`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct Telemetry {
+    pub id: u32,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub battery: f32,
+    pub status: String,
+    pub ts_ms: u128,
+
+    /// Velocity (east, north, up), units/s.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vel: Option<[f32; 3]>,
+    /// Horizontal speed over ground, units/s.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ground_speed: Option<f32>,
+    /// Heading / yaw in degrees, 0 = north (+y), clockwise.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub heading: Option<f32>,
+    /// Pitch in degrees, nose up positive.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pitch: Option<f32>,
+    /// Roll in degrees, right wing down positive.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub roll: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gps_fix: Option<GpsFix>,
+    /// Satellites used in the fix.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub satellites: Option<u8>,
+    /// Per-drone packet counter, incremented by the sender for every packet.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seq: Option<u32>,
+    /// WGS84 latitude in degrees. Sent together with `lon`; the dashboard projects
+    /// geodetic packets into local x/y/z metres around its home point.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lat: Option<f64>,
+    /// WGS84 longitude in degrees.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lon: Option<f64>,
+    /// Altitude in metres above the WGS84 ellipsoid.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alt: Option<f32>,
+}
+
+/// GPS fix quality, ordered from worst to best.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+#[repr(u8)]
+pub enum GpsFix {
+    NoFix = 0,
+    Fix2d = 1,
+    Fix3d = 2,
+    Dgps = 3,
+    RtkFloat = 4,
+    RtkFixed = 5,
+}
+
+impl GpsFix {
+    pub fn from_u8(v: u8) -> Option<Self> {
+        Some(match v {
+            0 => GpsFix::NoFix,
+            1 => GpsFix::Fix2d,
+            2 => GpsFix::Fix3d,
+            3 => GpsFix::Dgps,
+            4 => GpsFix::RtkFloat,
+            5 => GpsFix::RtkFixed,
+            _ => return None,
+        })
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            GpsFix::NoFix => "No fix",
+            GpsFix::Fix2d => "2D",
+            GpsFix::Fix3d => "3D",
+            GpsFix::Dgps => "DGPS",
+            GpsFix::RtkFloat => "RTK float",
+            GpsFix::RtkFixed => "RTK fixed",
+        }
+    }
+}
+
+/// Why a datagram could not be turned into a valid [`Telemetry`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecodeError {
+    /// Payload is not UTF-8 text.
+    Utf8,
+    /// Payload is text but not a `Telemetry` JSON object.
+    Json(String),
+    /// Payload carries the binary magic but the frame is malformed.
+    Binary(&'static str),
+    /// Payload decoded but a field is not a finite number, or lat/lon come unpaired.
+    Invalid(&'static str),
+    /// Payload decoded but a field is outside its plausible range.
+    OutOfRange { field: &'static str, value: f64 },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Utf8 => write!(f, "payload is not valid UTF-8"),
+            DecodeError::Json(e) => write!(f, "malformed JSON: {e}"),
+            DecodeError::Binary(e) => write!(f, "malformed binary frame: {e}"),
+            DecodeError::Invalid(field) => write!(f, "invalid value for `{field}`"),
+            DecodeError::OutOfRange { field, value } => {
+                write!(f, "`{field}` out of range: {value}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+impl DecodeError {
+    /// Category for counting rejects: the kind of failure, and the field for value
+    /// errors, without the details that differ per packet.
+    pub fn reason(&self) -> String {
+        match self {
+            DecodeError::Utf8 => "not UTF-8".to_string(),
+            DecodeError::Json(_) => "malformed JSON".to_string(),
+            DecodeError::Binary(e) => format!("binary: {e}"),
+            DecodeError::Invalid(field) => format!("invalid {field}"),
+            DecodeError::OutOfRange { field, .. } => format!("{field} out of range"),
+        }
+    }
+}
+
+/// `value` must be finite and within `range`.
+fn check(field: &'static str, value: f64, range: RangeInclusive<f64>) -> Result<(), DecodeError> {
+    if !value.is_finite() {
+        Err(DecodeError::Invalid(field))
+    } else if !range.contains(&value) {
+        Err(DecodeError::OutOfRange { field, value })
+    } else {
+        Ok(())
+    }
+}
+
+impl Telemetry {
+    /// Serialize to the JSON wire format.
+    pub fn encode_json(&self) -> Vec<u8> {
+        serde_json::to_vec(self).expect("Telemetry always serializes")
+    }
+
+    /// Serialize using the given wire format.
+    pub fn encode(&self, format: WireFormat) -> Vec<u8> {
+        match format {
+            WireFormat::Json => self.encode_json(),
+            WireFormat::Binary => wire::encode(self),
+        }
+    }
+
+    /// Sanity checks: every number must be finite and plausible (see the `*_RANGE`
+    /// constants), `ts_ms` no more than [`MAX_CLOCK_AHEAD_MS`] ahead of this
+    /// machine's clock, and lat/lon come as a pair.
+    pub fn validate(&self) -> Result<(), DecodeError> {
+        let latest = now_ms() + MAX_CLOCK_AHEAD_MS;
+        check("ts_ms", self.ts_ms as f64, 0.0..=latest as f64)?;
+        check("x", self.x as f64, COORDINATE_RANGE)?;
+        check("y", self.y as f64, COORDINATE_RANGE)?;
+        check("z", self.z as f64, ALTITUDE_RANGE)?;
+        check("battery", self.battery as f64, BATTERY_RANGE)?;
+        for c in self.vel.into_iter().flatten() {
+            check("vel", c as f64, -MAX_SPEED..=MAX_SPEED)?;
+        }
+        let optional = [
+            ("ground_speed", self.ground_speed, 0.0..=MAX_SPEED),
+            ("heading", self.heading, -360.0..=360.0),
+            ("pitch", self.pitch, -90.0..=90.0),
+            ("roll", self.roll, -180.0..=180.0),
+            ("alt", self.alt, ALTITUDE_RANGE),
+        ];
+        for (name, v, range) in optional {
+            if let Some(v) = v {
+                check(name, v as f64, range)?;
+            }
+        }
+        match (self.lat, self.lon) {
+            (Some(lat), Some(lon)) => {
+                check("lat", lat, -90.0..=90.0)?;
+                check("lon", lon, -180.0..=180.0)?;
+            }
+            (None, None) => {}
+            (Some(_), None) => return Err(DecodeError::Invalid("lon")),
+            (None, Some(_)) => return Err(DecodeError::Invalid("lat")),
+        }
+        Ok(())
+    }
+}
+
+/// Decode and validate a single datagram, auto-detecting JSON or binary framing.
+pub fn decode(buf: &[u8]) -> Result<Telemetry, DecodeError> {
+    let t = if wire::is_binary(buf) {
+        wire::decode(buf)?
+    } else {
+        decode_json(buf)?
+    };
+    t.validate()?;
+    Ok(t)
+}
+
+fn decode_json(buf: &[u8]) -> Result<Telemetry, DecodeError> {
+    let msg = std::str::from_utf8(buf).map_err(|_| DecodeError::Utf8)?;
+    serde_json::from_str(msg).map_err(|e| DecodeError::Json(e.to_string()))
+}
+
+/// Milliseconds since the Unix epoch, the unit of `ts_ms`.
+pub fn now_ms() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn valid() -> Telemetry {
+        Telemetry {
+            id: 7,
+            x: 12.5,
+            y: -3.0,
+            z: 40.0,
+            battery: 80.0,
+            status: "OK".into(),
+            ts_ms: 1_700_000_000_123,
+            vel: Some([1.0, 2.0, 0.0]),
+            heading: Some(90.0),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn plausible_packets_pass() {
+        assert_eq!(valid().validate(), Ok(()));
+        // Simulated clocks may start anywhere, and a sender may run a little ahead
+        for ts_ms in [0, now_ms() + 60_000] {
+            assert_eq!(Telemetry { ts_ms, ..valid() }.validate(), Ok(()));
+        }
+        let geodetic = Telemetry {
+            lat: Some(47.4),
+            lon: Some(8.5),
+            alt: Some(500.0),
+            ..valid()
+        };
+        assert_eq!(geodetic.validate(), Ok(()));
+    }
+
+    #[test]
+    fn implausible_values_are_rejected() {
+        let out_of_range = |t: Telemetry| match t.validate() {
+            Err(DecodeError::OutOfRange { field, .. }) => field,
+            other => panic!("expected out of range, got {other:?}"),
+        };
+        assert_eq!(out_of_range(Telemetry { battery: 101.0, ..valid() }), "battery");
+        assert_eq!(out_of_range(Telemetry { z: 1.0e6, ..valid() }), "z");
+        assert_eq!(
+            out_of_range(Telemetry { vel: Some([0.0, 400.0, 0.0]), ..valid() }),
+            "vel"
+        );
+        assert_eq!(out_of_range(Telemetry { pitch: Some(95.0), ..valid() }), "pitch");
+        assert_eq!(
+            out_of_range(Telemetry { lat: Some(91.0), lon: Some(0.0), ..valid() }),
+            "lat"
+        );
+        // Microseconds sent as milliseconds, or a clock set days ahead
+        let late = now_ms() + MAX_CLOCK_AHEAD_MS + 60_000;
+        for ts_ms in [1_700_000_000_123_000, late] {
+            assert_eq!(out_of_range(Telemetry { ts_ms, ..valid() }), "ts_ms");
+        }
+
+        let x = Telemetry { x: f32::NAN, ..valid() };
+        assert_eq!(x.validate(), Err(DecodeError::Invalid("x")));
+        let half = Telemetry { lat: Some(47.4), ..valid() };
+        assert_eq!(half.validate(), Err(DecodeError::Invalid("lon")));
+    }
+
+    #[test]
+    fn decode_reports_why() {
+        assert_eq!(decode(&valid().encode_json()), Ok(valid()));
+        assert_eq!(decode(&[0xff, 0xfe]), Err(DecodeError::Utf8));
+        assert!(matches!(decode(b"{\"id\": 1}"), Err(DecodeError::Json(_))));
+        let bad = Telemetry { battery: -5.0, ..valid() }.encode_json();
+        let err = decode(&bad).unwrap_err();
+        assert_eq!(err.reason(), "battery out of range");
+        assert_eq!(err.to_string(), "`battery` out of range: -5");
+    }
+}
@@ -0,0 +1,263 @@
+//! Offline raster map tiles in the Web Mercator ("slippy map") scheme.
+//!
+//! Two sources are supported, both read from local disk:
+//!
+//! * a directory laid out as `{z}/{x}/{y}.png` (or `.jpg` / `.jpeg`), XYZ row order;
+//! * an MBTiles file (SQLite), whose `tiles` table stores rows in TMS order
+//!   (flipped vertically relative to XYZ).
+//!
+//! Only lookup and decoding live here; the dashboard turns decoded tiles into
+//! textures and places them with its camera.
+
+use rusqlite::{Connection, OpenFlags, OptionalExtension};
+use std::{
+    f64::consts::PI,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Web Mercator stops here; beyond it tiles don't exist.
+pub const MAX_LATITUDE: f64 = 85.051_128_78;
+
+/// Ground resolution of zoom 0 at the equator, metres per pixel of a 256 px tile.
+const EQUATOR_M_PER_PX: f64 = 156_543.033_928;
+
+/// Deepest zoom level read from a source; deeper directories or rows are ignored.
+pub const MAX_ZOOM: u8 = 22;
+
+const EXTENSIONS: [&str; 3] = ["png", "jpg", "jpeg"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TileId {
+    pub z: u8,
+    pub x: u32,
+    pub y: u32,
+}
+
+impl TileId {
+    /// Latitude/longitude of the tile's north-west corner; `(x + 1, y + 1)` gives
+    /// the south-east one.
+    pub fn corner(z: u8, x: u32, y: u32) -> (f64, f64) {
+        let n = tiles_per_side(z);
+        let lon = x as f64 / n * 360.0 - 180.0;
+        let lat = (PI * (1.0 - 2.0 * y as f64 / n)).sinh().atan().to_degrees();
+        (lat, lon)
+    }
+}
+
+/// Fractional tile coordinates of a point at zoom `z`.
+pub fn tile_xy(lat: f64, lon: f64, z: u8) -> (f64, f64) {
+    let n = tiles_per_side(z);
+    let lat = lat.clamp(-MAX_LATITUDE, MAX_LATITUDE).to_radians();
+    let x = (lon + 180.0) / 360.0 * n;
+    let y = (1.0 - (lat.tan() + 1.0 / lat.cos()).ln() / PI) / 2.0 * n;
+    (x, y)
+}
+
+/// Tiles along each side of the world at zoom `z`.
+pub fn tiles_per_side(z: u8) -> f64 {
+    2f64.powi(z.into())
+}
+
+/// Zoom level whose pixels are about `m_per_px` metres wide at latitude `lat`.
+pub fn zoom_for(lat: f64, m_per_px: f64) -> f64 {
+    (EQUATOR_M_PER_PX * lat.to_radians().cos() / m_per_px.max(1e-6)).log2()
+}
+
+/// A decoded tile, RGBA8 row-major.
+pub struct TileImage {
+    pub width: usize,
+    pub height: usize,
+    pub rgba: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub enum TileError {
+    Io(io::Error),
+    Sqlite(rusqlite::Error),
+    Decode(image::ImageError),
+    /// The path is neither a tile directory nor an MBTiles file.
+    Unrecognized(PathBuf),
+    /// The source opened fine but holds no tiles.
+    Empty(PathBuf),
+}
+
+impl fmt::Display for TileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TileError::Io(e) => write!(f, "cannot read tiles: {e}"),
+            TileError::Sqlite(e) => write!(f, "cannot read MBTiles: {e}"),
+            TileError::Decode(e) => write!(f, "cannot decode tile: {e}"),
+            TileError::Unrecognized(p) => write!(
+                f,
+                "{} is neither a {{z}}/{{x}}/{{y}} tile directory nor an .mbtiles file",
+                p.display()
+            ),
+            TileError::Empty(p) => write!(f, "{} contains no tiles", p.display()),
+        }
+    }
+}
+
+impl std::error::Error for TileError {}
+
+pub enum TileSource {
+    Dir { root: PathBuf },
+    MbTiles { db: Connection },
+}
+
+impl TileSource {
+    /// Open a tile directory or an MBTiles file.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, TileError> {
+        let path = path.as_ref();
+        if path.is_dir() {
+            return Ok(TileSource::Dir {
+                root: path.to_path_buf(),
+            });
+        }
+        if !path.is_file() {
+            return Err(TileError::Io(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} does not exist", path.display()),
+            )));
+        }
+        let db = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .map_err(TileError::Sqlite)?;
+        // Cheap schema check so a random file fails now rather than per tile
+        db.prepare("SELECT tile_data FROM tiles LIMIT 1")
+            .map_err(|_| TileError::Unrecognized(path.to_path_buf()))?;
+        Ok(TileSource::MbTiles { db })
+    }
+
+    /// Lowest and highest zoom level present, if any tiles exist. Levels above
+    /// [`MAX_ZOOM`] are ignored.
+    pub fn zoom_range(&self) -> Result<Option<(u8, u8)>, TileError> {
+        match self {
+            TileSource::Dir { root } => {
+                let mut range: Option<(u8, u8)> = None;
+                for entry in fs::read_dir(root).map_err(TileError::Io)? {
+                    let entry = entry.map_err(TileError::Io)?;
+                    let Some(z) = entry
+                        .file_name()
+                        .to_str()
+                        .and_then(|n| n.parse::<u8>().ok())
+                        .filter(|&z| z <= MAX_ZOOM)
+                    else {
+                        continue;
+                    };
+                    if entry.path().is_dir() {
+                        range = Some(range.map_or((z, z), |(lo, hi)| (lo.min(z), hi.max(z))));
+                    }
+                }
+                Ok(range)
+            }
+            TileSource::MbTiles { db } => {
+                let (lo, hi): (Option<u8>, Option<u8>) = db
+                    .query_row(
+                        "SELECT MIN(zoom_level), MAX(zoom_level) FROM tiles \
+                         WHERE zoom_level BETWEEN 0 AND ?1",
+                        [MAX_ZOOM],
+                        |r| Ok((r.get(0)?, r.get(1)?)),
+                    )
+                    .map_err(TileError::Sqlite)?;
+                Ok(lo.zip(hi))
+            }
+        }
+    }
+
+    /// Raw encoded bytes of a tile, `None` if the source doesn't have it.
+    pub fn read(&self, id: TileId) -> Result<Option<Vec<u8>>, TileError> {
+        match self {
+            TileSource::Dir { root } => {
+                let base = root.join(id.z.to_string()).join(id.x.to_string());
+                for ext in EXTENSIONS {
+                    match fs::read(base.join(format!("{}.{ext}", id.y))) {
+                        Ok(bytes) => return Ok(Some(bytes)),
+                        Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                        Err(e) => return Err(TileError::Io(e)),
+                    }
+                }
+                Ok(None)
+            }
+            TileSource::MbTiles { db } => {
+                // MBTiles rows count from the south
+                let Some(tms_y) = 1u32
+                    .checked_shl(id.z.into())
+                    .and_then(|n| (n - 1).checked_sub(id.y))
+                else {
+                    return Ok(None);
+                };
+                db.query_row(
+                    "SELECT tile_data FROM tiles \
+                     WHERE zoom_level = ?1 AND tile_column = ?2 AND tile_row = ?3",
+                    (id.z, id.x, tms_y),
+                    |r| r.get(0),
+                )
+                .optional()
+                .map_err(TileError::Sqlite)
+            }
+        }
+    }
+
+    /// Read and decode a tile.
+    pub fn load(&self, id: TileId) -> Result<Option<TileImage>, TileError> {
+        let Some(bytes) = self.read(id)? else {
+            return Ok(None);
+        };
+        let img = image::load_from_memory(&bytes)
+            .map_err(TileError::Decode)?
+            .to_rgba8();
+        Ok(Some(TileImage {
+            width: img.width() as usize,
+            height: img.height() as usize,
+            rgba: img.into_raw(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tile_math_matches_known_tiles() {
+        assert_eq!(TileId::corner(0, 0, 0).1, -180.0);
+        assert!((TileId::corner(0, 0, 0).0 - MAX_LATITUDE).abs() < 1e-6);
+        let (x, y) = tile_xy(0.0, 0.0, 1);
+        assert_eq!((x, y), (1.0, 1.0));
+        // Deep zooms must not overflow
+        let (x, _) = tile_xy(0.0, 0.0, u8::MAX);
+        assert!(x.is_finite() && x > 0.0);
+        assert!(TileId::corner(u8::MAX, 0, 0).1.is_finite());
+    }
+
+    #[test]
+    fn directory_zooms_outside_range_are_ignored() {
+        let root = std::env::temp_dir().join(format!("tiles-zoom-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        for name in ["3", "12", "40", "255", "osm"] {
+            fs::create_dir_all(root.join(name)).unwrap();
+        }
+        let source = TileSource::open(&root).unwrap();
+        assert_eq!(source.zoom_range().unwrap(), Some((3, 12)));
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn mbtiles_rows_are_flipped_and_deep_zooms_ignored() {
+        let db = Connection::open_in_memory().unwrap();
+        db.execute_batch(
+            "CREATE TABLE tiles (zoom_level INTEGER, tile_column INTEGER, \
+             tile_row INTEGER, tile_data BLOB);
+             INSERT INTO tiles VALUES (2, 1, 3, x'01');
+             INSERT INTO tiles VALUES (40, 0, 0, x'02');",
+        )
+        .unwrap();
+        let source = TileSource::MbTiles { db };
+        assert_eq!(source.zoom_range().unwrap(), Some((2, 2)));
+        assert_eq!(source.read(TileId { z: 2, x: 1, y: 0 }).unwrap(), Some(vec![1]));
+        assert_eq!(source.read(TileId { z: 2, x: 1, y: 3 }).unwrap(), None);
+        // Rows past the edge of the zoom, or zooms past 31, are simply absent
+        assert_eq!(source.read(TileId { z: 2, x: 0, y: 9 }).unwrap(), None);
+        assert_eq!(source.read(TileId { z: 40, x: 0, y: 0 }).unwrap(), None);
+    }
+}
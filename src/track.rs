@@ -0,0 +1,321 @@
+//! Per-drone position estimation.
+//!
+//! The original estimator is a fixed exponential moving average on x/y, applied per
+//! packet whatever the spacing. The Kalman estimator runs a constant-velocity model
+//! in 3D driven by the sender's `ts_ms`, so it copes with uneven packet spacing,
+//! yields a velocity and a covariance, and gates out measurements that disagree
+//! with the track.
+//!
+//! Axes are filtered independently: with isotropic process noise and a diagonal
+//! measurement covariance the 6-state filter decouples into three 2-state ones.
+//!
+//! During telemetry gaps either estimator can dead-reckon a drone forward from its
+//! velocity for a limited horizon, see [`crate::fusion::DroneState::predict`].
+
+use std::time::Duration;
+
+/// How a drone's displayed position is derived from its reports.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Estimator {
+    /// Exponential moving average of x/y per packet.
+    Ema,
+    /// Constant-velocity Kalman filter in 3D.
+    #[default]
+    Kalman,
+}
+
+// EMA smoothing for visual position (lower = smoother, higher = snappier)
+pub const EMA_ALPHA: f32 = 0.25;
+
+/// Initial velocity standard deviation when the sender doesn't report one, m/s.
+const INITIAL_SPEED_SIGMA: f32 = 10.0;
+
+/// Gaps longer than this restart the track instead of coasting through them.
+const MAX_COAST_S: f32 = 30.0;
+
+/// Estimator selection and Kalman tuning.
+#[derive(Debug, Clone, Copy)]
+pub struct TrackConfig {
+    pub estimator: Estimator,
+    /// Standard deviation of the unmodelled acceleration, m/s².
+    pub accel_noise: f32,
+    /// Standard deviation of a reported position on each axis, m.
+    pub position_noise: f32,
+    /// Squared Mahalanobis distance beyond which a measurement is an outlier
+    /// (16.3 is the 99.9% point of a chi-square with 3 degrees of freedom).
+    pub gate: f32,
+    /// Consecutive outliers after which the track restarts at the measurement:
+    /// the drone really did jump (GPS re-acquired, relocated by hand).
+    pub max_rejects: u32,
+    /// Silence after which a drone's position is extrapolated.
+    pub predict_after: Duration,
+    /// Longest extrapolation; zero disables dead reckoning.
+    pub predict_horizon: Duration,
+}
+
+impl Default for TrackConfig {
+    fn default() -> Self {
+        Self {
+            estimator: Estimator::default(),
+            accel_noise: 2.0,
+            position_noise: 1.5,
+            gate: 16.3,
+            max_rejects: 5,
+            predict_after: Duration::from_millis(500),
+            predict_horizon: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Position and velocity along one axis, with their covariance.
+#[derive(Debug, Clone, Copy)]
+struct Axis {
+    p: f32,
+    v: f32,
+    /// Covariance `[[pp, pv], [pv, vv]]`.
+    pp: f32,
+    pv: f32,
+    vv: f32,
+}
+
+impl Axis {
+    fn new(p: f32, v: Option<f32>, r: f32) -> Self {
+        Self {
+            p,
+            v: v.unwrap_or(0.0),
+            pp: r,
+            pv: 0.0,
+            vv: if v.is_some() { r } else { INITIAL_SPEED_SIGMA.powi(2) },
+        }
+    }
+
+    /// State `dt` seconds ahead under white acceleration noise of density `q`.
+    fn predicted(&self, dt: f32, q: f32) -> Axis {
+        Axis {
+            p: self.p + self.v * dt,
+            v: self.v,
+            pp: self.pp + 2.0 * dt * self.pv + dt * dt * self.vv + q * dt.powi(3) / 3.0,
+            pv: self.pv + dt * self.vv + q * dt * dt / 2.0,
+            vv: self.vv + q * dt,
+        }
+    }
+
+    fn correct(&mut self, z: f32, r: f32) {
+        let s = self.pp + r;
+        let (kp, kv) = (self.pp / s, self.pv / s);
+        let y = z - self.p;
+        self.p += kp * y;
+        self.v += kv * y;
+        self.vv -= kv * self.pv;
+        self.pv *= 1.0 - kp;
+        self.pp *= 1.0 - kp;
+    }
+}
+
+/// Constant-velocity Kalman track of one drone.
+#[derive(Debug, Clone)]
+pub struct Kalman {
+    axes: [Axis; 3],
+    /// Sender time of the state, ms.
+    ts_ms: u128,
+    /// Measurements gated out as outliers.
+    pub rejected: u64,
+    /// Times the track restarted after a run of outliers or a long gap.
+    pub resets: u64,
+    consecutive_rejects: u32,
+}
+
+impl Kalman {
+    /// Start a track at a first report, seeding the velocity if the sender gives one.
+    pub fn new(pos: [f32; 3], vel: Option<[f32; 3]>, ts_ms: u128, cfg: &TrackConfig) -> Self {
+        let r = cfg.position_noise.powi(2);
+        Self {
+            axes: std::array::from_fn(|i| Axis::new(pos[i], vel.map(|v| v[i]), r)),
+            ts_ms,
+            rejected: 0,
+            resets: 0,
+            consecutive_rejects: 0,
+        }
+    }
+
+    /// Fold in a position reported at `ts_ms`. Reports older than the state are
+    /// ignored; returns whether the measurement was used.
+    pub fn step(
+        &mut self,
+        pos: [f32; 3],
+        vel: Option<[f32; 3]>,
+        ts_ms: u128,
+        cfg: &TrackConfig,
+    ) -> bool {
+        if ts_ms < self.ts_ms {
+            return false;
+        }
+        let dt = (ts_ms - self.ts_ms) as f32 / 1000.0;
+        if dt > MAX_COAST_S {
+            self.restart(pos, vel, ts_ms, cfg);
+            return true;
+        }
+
+        let q = cfg.accel_noise.powi(2);
+        let r = cfg.position_noise.powi(2);
+        let predicted = self.axes.map(|a| a.predicted(dt, q));
+        let distance: f32 = predicted
+            .iter()
+            .zip(pos)
+            .map(|(a, z)| (z - a.p).powi(2) / (a.pp + r))
+            .sum();
+        if distance > cfg.gate {
+            self.rejected += 1;
+            self.consecutive_rejects += 1;
+            if self.consecutive_rejects >= cfg.max_rejects {
+                self.restart(pos, vel, ts_ms, cfg);
+                return true;
+            }
+            return false;
+        }
+
+        self.axes = predicted;
+        for (a, z) in self.axes.iter_mut().zip(pos) {
+            a.correct(z, r);
+        }
+        self.ts_ms = ts_ms;
+        self.consecutive_rejects = 0;
+        true
+    }
+
+    fn restart(&mut self, pos: [f32; 3], vel: Option<[f32; 3]>, ts_ms: u128, cfg: &TrackConfig) {
+        *self = Self {
+            rejected: self.rejected,
+            resets: self.resets + 1,
+            ..Self::new(pos, vel, ts_ms, cfg)
+        };
+    }
+
+    pub fn position(&self) -> [f32; 3] {
+        self.axes.map(|a| a.p)
+    }
+
+    pub fn velocity(&self) -> [f32; 3] {
+        self.axes.map(|a| a.v)
+    }
+
+    /// Horizontal speed.
+    pub fn speed(&self) -> f32 {
+        let [vx, vy, _] = self.velocity();
+        vx.hypot(vy)
+    }
+
+    /// Position variance per axis, m².
+    pub fn position_variance(&self) -> [f32; 3] {
+        self.axes.map(|a| a.pp)
+    }
+
+    /// Velocity variance per axis, (m/s)².
+    pub fn velocity_variance(&self) -> [f32; 3] {
+        self.axes.map(|a| a.vv)
+    }
+
+    /// Position and its variance per axis extrapolated `dt` seconds past the state.
+    pub fn predict(&self, dt: f32, cfg: &TrackConfig) -> ([f32; 3], [f32; 3]) {
+        let q = cfg.accel_noise.powi(2);
+        let ahead = self.axes.map(|a| a.predicted(dt.max(0.0), q));
+        (ahead.map(|a| a.p), ahead.map(|a| a.pp))
+    }
+
+    /// Sender time of the state, ms.
+    pub fn ts_ms(&self) -> u128 {
+        self.ts_ms
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A track fed 10 Hz reports of a drone flying east at 5 m/s for `n` reports.
+    fn flown(n: u128, cfg: &TrackConfig) -> Kalman {
+        let mut k = Kalman::new([0.0, 0.0, 10.0], None, 0, cfg);
+        for i in 1..n {
+            let t = i as f32 / 10.0;
+            assert!(k.step([5.0 * t, 0.0, 10.0], None, i * 100, cfg));
+        }
+        k
+    }
+
+    #[test]
+    fn straight_line_converges() {
+        let cfg = TrackConfig::default();
+        let k = flown(100, &cfg);
+        let [x, y, z] = k.position();
+        assert!((x - 49.5).abs() < 0.1, "x {x}");
+        assert!(y.abs() < 0.1 && (z - 10.0).abs() < 0.1);
+        let [vx, vy, _] = k.velocity();
+        assert!((vx - 5.0).abs() < 0.1 && vy.abs() < 0.1, "v {vx} {vy}");
+        // Tighter than a single report
+        assert!(k.position_variance()[0] < cfg.position_noise.powi(2));
+        assert!(k.velocity_variance()[0] < INITIAL_SPEED_SIGMA.powi(2) / 10.0);
+        assert_eq!((k.rejected, k.resets), (0, 0));
+    }
+
+    #[test]
+    fn single_outlier_is_rejected() {
+        let cfg = TrackConfig::default();
+        let mut k = flown(50, &cfg);
+        let before = k.position();
+        assert!(!k.step([200.0, 0.0, 10.0], None, 5000, &cfg));
+        assert_eq!((k.rejected, k.resets), (1, 0));
+        assert_eq!(k.position(), before);
+        assert_eq!(k.ts_ms(), 4900);
+        // The next good report is used as usual
+        assert!(k.step([25.5, 0.0, 10.0], None, 5100, &cfg));
+        assert!((k.position()[0] - 25.5).abs() < 0.2);
+    }
+
+    #[test]
+    fn run_of_outliers_resets() {
+        let cfg = TrackConfig::default();
+        let mut k = flown(50, &cfg);
+        for i in 1..cfg.max_rejects as u128 {
+            assert!(!k.step([500.0, 0.0, 10.0], None, 4900 + i * 100, &cfg));
+        }
+        assert!(k.step([500.0, 0.0, 10.0], None, 5400, &cfg));
+        assert_eq!((k.rejected, k.resets), (cfg.max_rejects as u64, 1));
+        assert_eq!(k.position(), [500.0, 0.0, 10.0]);
+        assert_eq!(k.ts_ms(), 5400);
+    }
+
+    #[test]
+    fn long_gap_restarts() {
+        let cfg = TrackConfig::default();
+        let mut k = flown(50, &cfg);
+        let late = 4900 + (MAX_COAST_S as u128 + 1) * 1000;
+        assert!(k.step([0.0, 0.0, 0.0], Some([1.0, 0.0, 0.0]), late, &cfg));
+        assert_eq!((k.rejected, k.resets), (0, 1));
+        assert_eq!(k.position(), [0.0; 3]);
+        assert_eq!(k.velocity(), [1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn stale_reports_are_ignored() {
+        let cfg = TrackConfig::default();
+        let mut k = flown(50, &cfg);
+        let before = k.position();
+        assert!(!k.step([0.0; 3], None, 1000, &cfg));
+        assert_eq!(k.position(), before);
+        assert_eq!(k.rejected, 0);
+    }
+
+    #[test]
+    fn prediction_uncertainty_grows() {
+        let cfg = TrackConfig::default();
+        let k = flown(50, &cfg);
+        let (now, var_now) = k.predict(0.0, &cfg);
+        let (ahead, var_ahead) = k.predict(2.0, &cfg);
+        assert_eq!(now, k.position());
+        assert!((ahead[0] - now[0] - 10.0).abs() < 0.2);
+        assert!(var_ahead.iter().zip(var_now).all(|(a, n)| *a > n));
+        assert!(k.predict(5.0, &cfg).1[0] > var_ahead[0]);
+        // Negative spans don't go back in time
+        assert_eq!(k.predict(-1.0, &cfg).0, now);
+    }
+}
@@ -0,0 +1,457 @@
+//! Compact binary encoding of [`Telemetry`].
+//!
+//! Every datagram starts with a fixed 8-byte header, all integers little-endian:
+//!
+//! ```text
+//! offset  size  field
+//!      0     2  magic   b"TF"
+//!      2     1  version (1 or 2)
+//!      3     1  message type (1 = telemetry)
+//!      4     2  body length in bytes
+//!      6     2  reserved, zero
+//! ```
+//!
+//! The telemetry body (version 1) is:
+//!
+//! ```text
+//!      0     4  id       u32
+//!      4     4  x        f32
+//!      8     4  y        f32
+//!     12     4  z        f32
+//!     16     4  battery  f32
+//!     20     8  ts_ms    u64
+//!     28     1  status length N
+//!     29     N  status   UTF-8
+//! ```
+//!
+//! Version 2 appends an extension block: a `u32` presence mask followed by the
+//! optional fields whose bits are set, in bit order:
+//!
+//! ```text
+//!   bit  field          encoding
+//!     0  vel            3 x f32
+//!     1  ground_speed   f32
+//!     2  heading        f32
+//!     3  pitch, roll    2 x f32
+//!     4  gps_fix        u8
+//!     5  satellites     u8
+//!     6  seq            u32
+//!     7  lat, lon       2 x f64
+//!     8  alt            f32
+//! ```
+//!
+//! Paired fields travel together: pitch without roll, or lat without lon, is left
+//! out rather than padded with a value the sender never gave.
+//!
+//! The body length must match the datagram, and the body must end where its last
+//! field does; anything left over marks a corrupt frame.
+//!
+//! Encoders emit version 1 whenever no optional field is set, so senders that only
+//! fill the original fields stay readable by old receivers.
+//!
+//! A JSON payload always starts with `{` (or whitespace), so the magic bytes are enough
+//! for a receiver to tell the two formats apart.
+
+use crate::telemetry::{DecodeError, GpsFix, Telemetry};
+
+pub const MAGIC: [u8; 2] = *b"TF";
+pub const VERSION: u8 = 2;
+pub const HEADER_LEN: usize = 8;
+
+pub const MSG_TELEMETRY: u8 = 1;
+
+const BODY_FIXED_LEN: usize = 29;
+
+const EXT_VEL: u32 = 1 << 0;
+const EXT_GROUND_SPEED: u32 = 1 << 1;
+const EXT_HEADING: u32 = 1 << 2;
+const EXT_ATTITUDE: u32 = 1 << 3;
+const EXT_GPS_FIX: u32 = 1 << 4;
+const EXT_SATELLITES: u32 = 1 << 5;
+const EXT_SEQ: u32 = 1 << 6;
+const EXT_LAT_LON: u32 = 1 << 7;
+const EXT_ALT: u32 = 1 << 8;
+const EXT_KNOWN: u32 = (1 << 9) - 1;
+
+/// Encoding used on the wire.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum WireFormat {
+    /// Human-readable JSON object per datagram
+    Json,
+    /// Versioned binary frame (see `wire` module docs)
+    Binary,
+}
+
+/// True if `buf` looks like a binary frame rather than JSON.
+pub fn is_binary(buf: &[u8]) -> bool {
+    buf.len() >= MAGIC.len() && buf[..MAGIC.len()] == MAGIC
+}
+
+fn ext_mask(t: &Telemetry) -> u32 {
+    let mut mask = 0;
+    if t.vel.is_some() {
+        mask |= EXT_VEL;
+    }
+    if t.ground_speed.is_some() {
+        mask |= EXT_GROUND_SPEED;
+    }
+    if t.heading.is_some() {
+        mask |= EXT_HEADING;
+    }
+    if t.pitch.is_some() && t.roll.is_some() {
+        mask |= EXT_ATTITUDE;
+    }
+    if t.gps_fix.is_some() {
+        mask |= EXT_GPS_FIX;
+    }
+    if t.satellites.is_some() {
+        mask |= EXT_SATELLITES;
+    }
+    if t.seq.is_some() {
+        mask |= EXT_SEQ;
+    }
+    if t.lat.is_some() && t.lon.is_some() {
+        mask |= EXT_LAT_LON;
+    }
+    if t.alt.is_some() {
+        mask |= EXT_ALT;
+    }
+    mask
+}
+
+pub fn encode(t: &Telemetry) -> Vec<u8> {
+    // Status is length-prefixed with a single byte; truncate on a char boundary
+    let mut status_len = t.status.len().min(u8::MAX as usize);
+    while !t.status.is_char_boundary(status_len) {
+        status_len -= 1;
+    }
+    let status = &t.status.as_bytes()[..status_len];
+
+    let mask = ext_mask(t);
+    let mut body = Vec::with_capacity(BODY_FIXED_LEN + status.len() + 64);
+    body.extend_from_slice(&t.id.to_le_bytes());
+    body.extend_from_slice(&t.x.to_le_bytes());
+    body.extend_from_slice(&t.y.to_le_bytes());
+    body.extend_from_slice(&t.z.to_le_bytes());
+    body.extend_from_slice(&t.battery.to_le_bytes());
+    body.extend_from_slice(&(t.ts_ms as u64).to_le_bytes());
+    body.push(status.len() as u8);
+    body.extend_from_slice(status);
+
+    if mask != 0 {
+        body.extend_from_slice(&mask.to_le_bytes());
+        if let Some(v) = t.vel {
+            for c in v {
+                body.extend_from_slice(&c.to_le_bytes());
+            }
+        }
+        if let Some(v) = t.ground_speed {
+            body.extend_from_slice(&v.to_le_bytes());
+        }
+        if let Some(v) = t.heading {
+            body.extend_from_slice(&v.to_le_bytes());
+        }
+        if let (Some(pitch), Some(roll)) = (t.pitch, t.roll) {
+            body.extend_from_slice(&pitch.to_le_bytes());
+            body.extend_from_slice(&roll.to_le_bytes());
+        }
+        if let Some(v) = t.gps_fix {
+            body.push(v as u8);
+        }
+        if let Some(v) = t.satellites {
+            body.push(v);
+        }
+        if let Some(v) = t.seq {
+            body.extend_from_slice(&v.to_le_bytes());
+        }
+        if let (Some(lat), Some(lon)) = (t.lat, t.lon) {
+            body.extend_from_slice(&lat.to_le_bytes());
+            body.extend_from_slice(&lon.to_le_bytes());
+        }
+        if let Some(v) = t.alt {
+            body.extend_from_slice(&v.to_le_bytes());
+        }
+    }
+
+    let mut out = Vec::with_capacity(HEADER_LEN + body.len());
+    out.extend_from_slice(&MAGIC);
+    out.push(if mask != 0 { 2 } else { 1 });
+    out.push(MSG_TELEMETRY);
+    out.extend_from_slice(&(body.len() as u16).to_le_bytes());
+    out.extend_from_slice(&[0, 0]);
+    out.extend_from_slice(&body);
+    out
+}
+
+pub fn decode(buf: &[u8]) -> Result<Telemetry, DecodeError> {
+    if buf.len() < HEADER_LEN {
+        return Err(DecodeError::Binary("truncated header"));
+    }
+    if buf[..2] != MAGIC {
+        return Err(DecodeError::Binary("bad magic"));
+    }
+    let version = buf[2];
+    if !(1..=VERSION).contains(&version) {
+        return Err(DecodeError::Binary("unsupported version"));
+    }
+    if buf[3] != MSG_TELEMETRY {
+        return Err(DecodeError::Binary("unknown message type"));
+    }
+    let body_len = u16::from_le_bytes([buf[4], buf[5]]) as usize;
+    let body = buf
+        .get(HEADER_LEN..HEADER_LEN + body_len)
+        .ok_or(DecodeError::Binary("truncated body"))?;
+    if buf.len() != HEADER_LEN + body_len {
+        return Err(DecodeError::Binary("trailing bytes after body"));
+    }
+    if body.len() < BODY_FIXED_LEN {
+        return Err(DecodeError::Binary("truncated body"));
+    }
+
+    let mut r = Reader { buf: body, pos: 0 };
+    let id = r.u32()?;
+    let x = r.f32()?;
+    let y = r.f32()?;
+    let z = r.f32()?;
+    let battery = r.f32()?;
+    let ts_ms = r.u64()? as u128;
+    let status_len = r.u8()? as usize;
+    let status = r.take(status_len)?;
+    let status = std::str::from_utf8(status)
+        .map_err(|_| DecodeError::Binary("status not UTF-8"))?
+        .to_string();
+
+    let mut t = Telemetry {
+        id,
+        x,
+        y,
+        z,
+        battery,
+        status,
+        ts_ms,
+        ..Default::default()
+    };
+
+    if version >= 2 {
+        let mask = r.u32()?;
+        if mask & !EXT_KNOWN != 0 {
+            return Err(DecodeError::Binary("unknown extension field"));
+        }
+        if mask & EXT_VEL != 0 {
+            t.vel = Some([r.f32()?, r.f32()?, r.f32()?]);
+        }
+        if mask & EXT_GROUND_SPEED != 0 {
+            t.ground_speed = Some(r.f32()?);
+        }
+        if mask & EXT_HEADING != 0 {
+            t.heading = Some(r.f32()?);
+        }
+        if mask & EXT_ATTITUDE != 0 {
+            t.pitch = Some(r.f32()?);
+            t.roll = Some(r.f32()?);
+        }
+        if mask & EXT_GPS_FIX != 0 {
+            t.gps_fix =
+                Some(GpsFix::from_u8(r.u8()?).ok_or(DecodeError::Binary("bad gps_fix"))?);
+        }
+        if mask & EXT_SATELLITES != 0 {
+            t.satellites = Some(r.u8()?);
+        }
+        if mask & EXT_SEQ != 0 {
+            t.seq = Some(r.u32()?);
+        }
+        if mask & EXT_LAT_LON != 0 {
+            t.lat = Some(r.f64()?);
+            t.lon = Some(r.f64()?);
+        }
+        if mask & EXT_ALT != 0 {
+            t.alt = Some(r.f32()?);
+        }
+    }
+    if r.pos != body.len() {
+        return Err(DecodeError::Binary("trailing bytes in body"));
+    }
+
+    Ok(t)
+}
+
+/// Little-endian cursor over a frame body.
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], DecodeError> {
+        let s = self
+            .buf
+            .get(self.pos..self.pos + n)
+            .ok_or(DecodeError::Binary("truncated body"))?;
+        self.pos += n;
+        Ok(s)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, DecodeError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn u64(&mut self) -> Result<u64, DecodeError> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    fn f32(&mut self) -> Result<f32, DecodeError> {
+        Ok(f32::from_le_bytes(self.array()?))
+    }
+
+    fn f64(&mut self) -> Result<f64, DecodeError> {
+        Ok(f64::from_le_bytes(self.array()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::telemetry;
+
+    fn base() -> Telemetry {
+        Telemetry {
+            id: 7,
+            x: 12.5,
+            y: -3.25,
+            z: 40.0,
+            battery: 87.5,
+            status: "OK".to_string(),
+            ts_ms: 1_700_000_000_123,
+            ..Default::default()
+        }
+    }
+
+    fn full() -> Telemetry {
+        Telemetry {
+            vel: Some([1.0, -2.0, 0.5]),
+            ground_speed: Some(2.2),
+            heading: Some(270.0),
+            pitch: Some(-4.0),
+            roll: Some(3.5),
+            gps_fix: Some(GpsFix::RtkFixed),
+            satellites: Some(17),
+            seq: Some(0xdead_beef),
+            lat: Some(47.397_742),
+            lon: Some(8.545_594),
+            alt: Some(488.0),
+            ..base()
+        }
+    }
+
+    /// Same packet through JSON and binary decodes to the same value.
+    fn assert_round_trip(t: &Telemetry) {
+        let from_binary = telemetry::decode(&encode(t)).unwrap();
+        let from_json = telemetry::decode(&t.encode_json()).unwrap();
+        assert_eq!(&from_binary, t);
+        assert_eq!(from_binary, from_json);
+    }
+
+    #[test]
+    fn base_fields_use_version_1() {
+        let t = base();
+        let buf = encode(&t);
+        assert_eq!(buf[2], 1);
+        assert_round_trip(&t);
+    }
+
+    #[test]
+    fn every_extension_round_trips() {
+        let t = full();
+        assert_eq!(encode(&t)[2], 2);
+        assert_round_trip(&t);
+    }
+
+    #[test]
+    fn each_extension_bit_round_trips_alone() {
+        let full = full();
+        let singles = [
+            Telemetry { vel: full.vel, ..base() },
+            Telemetry { ground_speed: full.ground_speed, ..base() },
+            Telemetry { heading: full.heading, ..base() },
+            Telemetry { pitch: full.pitch, roll: full.roll, ..base() },
+            Telemetry { gps_fix: full.gps_fix, ..base() },
+            Telemetry { satellites: full.satellites, ..base() },
+            Telemetry { seq: full.seq, ..base() },
+            Telemetry { lat: full.lat, lon: full.lon, ..base() },
+            Telemetry { alt: full.alt, ..base() },
+        ];
+        for (bit, t) in singles.iter().enumerate() {
+            let buf = encode(t);
+            let mask_at = HEADER_LEN + BODY_FIXED_LEN + t.status.len();
+            let mask = u32::from_le_bytes(buf[mask_at..mask_at + 4].try_into().unwrap());
+            assert_eq!(mask, 1 << bit);
+            assert_round_trip(t);
+        }
+    }
+
+    #[test]
+    fn half_pairs_are_not_padded() {
+        let t = Telemetry {
+            pitch: Some(5.0),
+            lat: Some(47.0),
+            ..base()
+        };
+        let decoded = decode(&encode(&t)).unwrap();
+        assert_eq!(decoded.pitch, None);
+        assert_eq!(decoded.roll, None);
+        assert_eq!(decoded.lat, None);
+        assert_eq!(decoded.lon, None);
+    }
+
+    #[test]
+    fn truncated_frames_are_rejected() {
+        let buf = encode(&full());
+        for len in 0..buf.len() {
+            assert!(decode(&buf[..len]).is_err(), "accepted {len} of {} bytes", buf.len());
+        }
+    }
+
+    #[test]
+    fn unknown_extension_bit_is_rejected() {
+        let t = Telemetry { alt: Some(10.0), ..base() };
+        let mut buf = encode(&t);
+        let mask_at = HEADER_LEN + BODY_FIXED_LEN + t.status.len();
+        buf[mask_at + 1] |= 0x02; // bit 9
+        assert_eq!(decode(&buf), Err(DecodeError::Binary("unknown extension field")));
+    }
+
+    #[test]
+    fn bad_status_is_a_binary_error() {
+        let mut buf = encode(&base());
+        let status_at = HEADER_LEN + BODY_FIXED_LEN;
+        buf[status_at] = 0xff;
+        let err = decode(&buf).unwrap_err();
+        assert_eq!(err, DecodeError::Binary("status not UTF-8"));
+        assert_eq!(err.reason(), "binary: status not UTF-8");
+    }
+
+    #[test]
+    fn leftover_bytes_are_rejected() {
+        for t in [base(), full()] {
+            // Past the declared body
+            let mut buf = encode(&t);
+            buf.push(0);
+            assert_eq!(decode(&buf), Err(DecodeError::Binary("trailing bytes after body")));
+
+            // Inside the declared body, after the last field
+            let mut buf = encode(&t);
+            buf.extend_from_slice(&[0, 0]);
+            let body_len = u16::from_le_bytes([buf[4], buf[5]]) + 2;
+            buf[4..6].copy_from_slice(&body_len.to_le_bytes());
+            assert_eq!(decode(&buf), Err(DecodeError::Binary("trailing bytes in body")));
+        }
+    }
+}